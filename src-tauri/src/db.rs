@@ -1,4 +1,4 @@
-use rusqlite::{Connection, Result as SqlResult};
+use rusqlite::{Connection, OptionalExtension, Result as SqlResult};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
@@ -17,6 +17,17 @@ pub struct DownloadHistoryEntry {
     pub downloaded_at: String,
 }
 
+/// A cached MusicBrainz enrichment result, keyed by source URL so the
+/// fingerprint/MusicBrainz lookup isn't repeated for the same track.
+#[derive(Debug, Clone)]
+pub struct CachedEnrichment {
+    pub artist: String,
+    pub title: String,
+    pub album: Option<String>,
+    pub release_year: Option<i64>,
+    pub track_number: Option<i64>,
+}
+
 /// Get the path to the database file
 fn get_db_path() -> PathBuf {
     dirs::config_dir()
@@ -56,6 +67,19 @@ pub fn init_db() -> SqlResult<Connection> {
         [],
     )?;
 
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS metadata_enrichment_cache (
+            source_key TEXT PRIMARY KEY,
+            artist TEXT NOT NULL,
+            title TEXT NOT NULL,
+            album TEXT,
+            release_year INTEGER,
+            track_number INTEGER,
+            cached_at TEXT NOT NULL DEFAULT (datetime('now'))
+        )",
+        [],
+    )?;
+
     Ok(conn)
 }
 
@@ -153,3 +177,50 @@ pub fn clear_history() -> SqlResult<()> {
     conn.execute("DELETE FROM download_history", [])?;
     Ok(())
 }
+
+/// Look up a cached MusicBrainz enrichment result for `source_key`.
+pub fn get_cached_enrichment(source_key: &str) -> SqlResult<Option<CachedEnrichment>> {
+    let conn = init_db()?;
+    conn.query_row(
+        "SELECT artist, title, album, release_year, track_number
+         FROM metadata_enrichment_cache
+         WHERE source_key = ?1",
+        [source_key],
+        |row| {
+            Ok(CachedEnrichment {
+                artist: row.get(0)?,
+                title: row.get(1)?,
+                album: row.get(2)?,
+                release_year: row.get(3)?,
+                track_number: row.get(4)?,
+            })
+        },
+    )
+    .optional()
+}
+
+/// Cache a MusicBrainz enrichment result for `source_key`, overwriting any
+/// previous entry.
+pub fn cache_enrichment(source_key: &str, enrichment: &CachedEnrichment) -> SqlResult<()> {
+    let conn = init_db()?;
+    conn.execute(
+        "INSERT INTO metadata_enrichment_cache (source_key, artist, title, album, release_year, track_number)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(source_key) DO UPDATE SET
+            artist = excluded.artist,
+            title = excluded.title,
+            album = excluded.album,
+            release_year = excluded.release_year,
+            track_number = excluded.track_number,
+            cached_at = datetime('now')",
+        rusqlite::params![
+            source_key,
+            enrichment.artist,
+            enrichment.title,
+            enrichment.album,
+            enrichment.release_year,
+            enrichment.track_number,
+        ],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,177 @@
+use std::path::Path;
+
+use crate::db::{self, CachedEnrichment};
+use crate::formats;
+
+/// Confirmed/corrected tag fields from a MusicBrainz lookup, used to override
+/// the heuristic `parse_artist_title` split when a match is found.
+pub struct EnrichedTags {
+    pub artist: String,
+    pub title: String,
+    pub album: Option<String>,
+    pub release_year: Option<u32>,
+    pub track_number: Option<u32>,
+}
+
+/// Sample rate Chromaprint expects fingerprint input at.
+const FINGERPRINT_SAMPLE_RATE: u32 = 11025;
+
+/// Best-effort metadata enrichment via AcoustID fingerprint lookup + MusicBrainz
+/// recording details. Cached by `source_key` (the download URL) so repeat
+/// downloads of the same track don't re-fingerprint or re-query. Never
+/// returns an error - this is an optional confirmation step on top of the
+/// heuristic title/artist parse, like `fetch_lyrics`.
+pub fn enrich(source_key: &str, audio_path: &Path, acoustid_api_key: &str) -> Option<EnrichedTags> {
+    if let Ok(Some(cached)) = db::get_cached_enrichment(source_key) {
+        return Some(from_cached(cached));
+    }
+
+    let (fingerprint, duration_secs) = compute_fingerprint(audio_path).ok()?;
+    let recording_id = acoustid_lookup(acoustid_api_key, duration_secs, &fingerprint)?;
+    let tags = musicbrainz_lookup(&recording_id)?;
+
+    if let Err(e) = db::cache_enrichment(source_key, &to_cached(&tags)) {
+        eprintln!("Warning: failed to cache metadata enrichment: {}", e);
+    }
+
+    Some(tags)
+}
+
+fn from_cached(cached: CachedEnrichment) -> EnrichedTags {
+    EnrichedTags {
+        artist: cached.artist,
+        title: cached.title,
+        album: cached.album,
+        release_year: cached.release_year.map(|y| y as u32),
+        track_number: cached.track_number.map(|t| t as u32),
+    }
+}
+
+fn to_cached(tags: &EnrichedTags) -> CachedEnrichment {
+    CachedEnrichment {
+        artist: tags.artist.clone(),
+        title: tags.title.clone(),
+        album: tags.album.clone(),
+        release_year: tags.release_year.map(|y| y as i64),
+        track_number: tags.track_number.map(|t| t as i64),
+    }
+}
+
+/// Decode `audio_path`, fold it down to mono, resample to the rate Chromaprint
+/// expects, and return its compressed fingerprint string alongside the
+/// track's duration in seconds (AcoustID uses both to narrow its search).
+fn compute_fingerprint(audio_path: &Path) -> Result<(String, u32), String> {
+    let mut interleaved: Vec<i16> = Vec::new();
+    let (source_rate, channels) = formats::decode_to_interleaved_i16(audio_path, |chunk| {
+        interleaved.extend_from_slice(chunk);
+        Ok(())
+    })?;
+
+    let channels = channels as usize;
+    let mono: Vec<i16> = interleaved
+        .chunks(channels)
+        .map(|frame| (frame.iter().map(|&s| s as i32).sum::<i32>() / channels as i32) as i16)
+        .collect();
+
+    let duration_secs = (mono.len() as u64 / source_rate.max(1) as u64) as u32;
+    let resampled = resample_mono(&mono, source_rate, FINGERPRINT_SAMPLE_RATE);
+
+    let mut printer = chromaprint::Chromaprint::new();
+    if !printer.start(FINGERPRINT_SAMPLE_RATE as i32, 1) {
+        return Err("Failed to start fingerprinter".to_string());
+    }
+    if !printer.feed(&resampled) {
+        return Err("Failed to feed samples to fingerprinter".to_string());
+    }
+    if !printer.finish() {
+        return Err("Failed to finalize fingerprint".to_string());
+    }
+
+    let fingerprint = printer.fingerprint().ok_or("No fingerprint produced")?;
+    Ok((fingerprint, duration_secs))
+}
+
+/// Nearest-neighbor decimation, not a bandlimited resample - fine here since
+/// Chromaprint's own chroma features are coarse enough that aliasing at this
+/// sample rate doesn't meaningfully change the fingerprint.
+fn resample_mono(samples: &[i16], from_rate: u32, to_rate: u32) -> Vec<i16> {
+    if from_rate == to_rate || samples.is_empty() {
+        return samples.to_vec();
+    }
+
+    let ratio = from_rate as f64 / to_rate as f64;
+    let out_len = (samples.len() as f64 / ratio) as usize;
+    (0..out_len)
+        .map(|i| samples[((i as f64 * ratio) as usize).min(samples.len() - 1)])
+        .collect()
+}
+
+/// Query AcoustID for the MusicBrainz recording id matching `fingerprint`.
+fn acoustid_lookup(api_key: &str, duration_secs: u32, fingerprint: &str) -> Option<String> {
+    let duration_secs = duration_secs.to_string();
+    let body: serde_json::Value = reqwest::blocking::Client::new()
+        .get("https://api.acoustid.org/v2/lookup")
+        .query(&[
+            ("client", api_key),
+            ("duration", duration_secs.as_str()),
+            ("fingerprint", fingerprint),
+            ("meta", "recordings"),
+        ])
+        .send()
+        .ok()?
+        .json()
+        .ok()?;
+    body.get("results")?
+        .as_array()?
+        .first()?
+        .get("recordings")?
+        .as_array()?
+        .first()?
+        .get("id")?
+        .as_str()
+        .map(str::to_string)
+}
+
+/// Fetch artist/title/album/year for a MusicBrainz recording id. Track
+/// number is left unset - it lives on the release's medium/track-list, which
+/// this lookup doesn't request.
+fn musicbrainz_lookup(recording_id: &str) -> Option<EnrichedTags> {
+    // `recording_id` comes from AcoustID's response, not something we mint
+    // ourselves, so it gets percent-encoded as a path segment like any other
+    // untrusted value - `push` handles that, unlike the raw `format!` this
+    // replaced. The `inc`/`fmt` suffix is a fixed literal (no external input),
+    // so it's left as MusicBrainz's documented `+`-joined query syntax rather
+    // than routed through `.query()`, which would percent-encode the `+` into
+    // `%2B` and risk it no longer being read as the `inc` list separator.
+    let mut url = reqwest::Url::parse("https://musicbrainz.org/ws/2/recording/").ok()?;
+    url.path_segments_mut().ok()?.push(recording_id);
+    url.set_query(Some("inc=artist-credits+releases&fmt=json"));
+
+    let client = reqwest::blocking::Client::builder()
+        .user_agent("rippr/0.1 ( https://github.com/iheanyi/rippr )")
+        .build()
+        .ok()?;
+    let body: serde_json::Value = client.get(url).send().ok()?.json().ok()?;
+
+    let title = body.get("title")?.as_str()?.to_string();
+    let artist = body
+        .get("artist-credit")?
+        .as_array()?
+        .iter()
+        .filter_map(|credit| credit.get("name").and_then(|n| n.as_str()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if artist.is_empty() {
+        return None;
+    }
+
+    let release = body.get("releases").and_then(|r| r.as_array()).and_then(|arr| arr.first());
+    let album = release.and_then(|r| r.get("title")).and_then(|t| t.as_str()).map(str::to_string);
+    let release_year = release
+        .and_then(|r| r.get("date"))
+        .and_then(|d| d.as_str())
+        .and_then(|d| d.get(0..4))
+        .and_then(|y| y.parse::<u32>().ok());
+
+    Some(EnrichedTags { artist, title, album, release_year, track_number: None })
+}
@@ -1,14 +1,10 @@
-use id3::TagLike;
-use mp3lame_encoder::{Builder, FlushNoGap, InterleavedPcm};
 use pyo3::prelude::*;
-use pyo3::types::{PyDict, PyList};
+use pyo3::types::PyDict;
 use serde::{Deserialize, Serialize};
 use std::fs::File;
-use std::io::Write;
-use std::mem::MaybeUninit;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Mutex, Once};
+use std::sync::{Arc, Mutex, Once};
 use tauri::Emitter;
 use std::collections::HashMap;
 use uuid::Uuid;
@@ -16,16 +12,46 @@ use uuid::Uuid;
 mod db;
 mod audio_analysis;
 mod waveform;
+mod formats;
+mod tagging;
+mod ytdlp;
+mod ytdlp_update;
+mod enrichment;
+mod template;
+mod spotify;
+mod lyrics;
 use db::DownloadHistoryEntry;
 use audio_analysis::AudioAnalysis;
 use waveform::WaveformPoint;
+use formats::{Mp3EncodingMode, OutputFormat, QualityPreset};
+use tagging::{Lyrics, SyncedLine, TrackTags};
+use ytdlp::{Backend as YtDlpBackend, CancelCheck, ExtractorOptions, ProgressHook};
 
-// Global cancellation flag
+// Global cancellation flag, used by the single-shot (non-queue) download commands
 static CANCEL_DOWNLOAD: AtomicBool = AtomicBool::new(false);
 
 // Global download queue
 lazy_static::lazy_static! {
     static ref DOWNLOAD_QUEUE: Mutex<HashMap<String, QueueItem>> = Mutex::new(HashMap::new());
+    // One cancellation token per in-flight queue item, so cancelling one
+    // download doesn't stop every other worker in the pool.
+    static ref DOWNLOAD_CONTROL: Mutex<HashMap<String, Arc<AtomicBool>>> = Mutex::new(HashMap::new());
+}
+
+/// Register a fresh cancellation token for a queue item about to start downloading.
+fn register_cancellation(id: &str) -> Arc<AtomicBool> {
+    let flag = Arc::new(AtomicBool::new(false));
+    if let Ok(mut control) = DOWNLOAD_CONTROL.lock() {
+        control.insert(id.to_string(), flag.clone());
+    }
+    flag
+}
+
+/// Drop a queue item's cancellation token once it's done (complete, failed, or cancelled).
+fn unregister_cancellation(id: &str) {
+    if let Ok(mut control) = DOWNLOAD_CONTROL.lock() {
+        control.remove(id);
+    }
 }
 
 /// Status of a queue item
@@ -54,6 +80,11 @@ pub struct QueueItem {
     pub error: Option<String>,
     #[serde(rename = "outputPath")]
     pub output_path: Option<String>,
+    /// Per-item output format override; falls back to `Settings::quality_preset` when unset.
+    pub format: Option<OutputFormat>,
+    /// 1-based position within the source playlist/album, carried through to
+    /// the tagged track number when set (e.g. from `add_playlist_to_queue`).
+    pub playlist_index: Option<u32>,
 }
 
 /// Progress update for a queue item
@@ -73,6 +104,13 @@ pub struct DownloadProgress {
     pub message: String,
 }
 
+/// Live progress from `convert_trimmed`'s decode/encode loop, reported as it
+/// runs on a blocking thread - samples encoded so far out of the clip's total.
+struct TrimProgress {
+    samples_encoded: u64,
+    total_samples: u64,
+}
+
 /// Error types for better error messages
 #[derive(Debug, Clone, Serialize)]
 pub enum DownloadError {
@@ -100,7 +138,7 @@ impl std::fmt::Display for DownloadError {
 static PYTHON_INIT: Once = Once::new();
 
 /// Initialize Python environment - looks for bundled Python or falls back to system Python
-fn init_python_env() {
+pub(crate) fn init_python_env() {
     PYTHON_INIT.call_once(|| {
         // Try to find bundled Python in the app resources
         if let Some(resource_dir) = get_resource_dir() {
@@ -189,6 +227,52 @@ pub struct DownloadResult {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Settings {
     pub download_dir: String,
+    #[serde(default)]
+    pub quality_preset: QualityPreset,
+    /// MP3 CBR bitrate `download_audio_trimmed` uses when its `encoding_mode`
+    /// argument is omitted, replacing the bitrate from `quality_preset` there.
+    /// The other download commands take the MP3 bitrate from `quality_preset`.
+    #[serde(default)]
+    pub mp3_encoding_mode: Mp3EncodingMode,
+    #[serde(default)]
+    pub ytdlp_backend: YtDlpBackend,
+    #[serde(default)]
+    pub extractor_options: ExtractorOptions,
+    /// Confirm/correct title, artist, album, and year via an AcoustID
+    /// fingerprint + MusicBrainz lookup after download. Off by default since
+    /// it requires network access and an API key.
+    #[serde(default)]
+    pub enrich_metadata: bool,
+    #[serde(default)]
+    pub acoustid_api_key: Option<String>,
+    /// Look up and embed lyrics (synced when available) after download. Off
+    /// by default since it requires network access.
+    #[serde(default)]
+    pub fetch_lyrics: bool,
+    /// Filename/folder template rendered for every download, e.g.
+    /// `"{albumartist}/{album}/{track:02} - {title}"`. May contain `/` to
+    /// organize output into subfolders. See `template::render`.
+    #[serde(default = "default_filename_template")]
+    pub filename_template: String,
+    /// Default number of queue items `start_queue` processes in parallel,
+    /// used when its `concurrency` argument is omitted.
+    #[serde(default = "default_queue_concurrency")]
+    pub queue_concurrency: usize,
+    /// Spotify Web API client-credentials used by `add_spotify_to_queue` to
+    /// resolve track/album/playlist URLs. Spotify doesn't serve audio, so
+    /// resolved tracks are matched to a YouTube video via `ytdlp::search`.
+    #[serde(default)]
+    pub spotify_client_id: Option<String>,
+    #[serde(default)]
+    pub spotify_client_secret: Option<String>,
+}
+
+fn default_queue_concurrency() -> usize {
+    3
+}
+
+fn default_filename_template() -> String {
+    template::DEFAULT_TEMPLATE.to_string()
 }
 
 impl Default for Settings {
@@ -199,110 +283,278 @@ impl Default for Settings {
             .to_string_lossy()
             .to_string();
 
-        Self { download_dir }
+        Self {
+            download_dir,
+            quality_preset: QualityPreset::default(),
+            mp3_encoding_mode: Mp3EncodingMode::default(),
+            ytdlp_backend: YtDlpBackend::default(),
+            extractor_options: ExtractorOptions::default(),
+            enrich_metadata: false,
+            acoustid_api_key: None,
+            fetch_lyrics: false,
+            filename_template: default_filename_template(),
+            queue_concurrency: default_queue_concurrency(),
+            spotify_client_id: None,
+            spotify_client_secret: None,
+        }
     }
 }
 
-#[derive(Debug, Deserialize)]
-struct YtDlpMetadata {
-    id: String,
-    title: String,
+/// Load settings from disk synchronously, falling back to defaults - used by
+/// internal helpers (like the yt-dlp backend config) that aren't themselves
+/// Tauri commands.
+fn load_settings() -> Settings {
+    let path = get_settings_path();
+    if !path.exists() {
+        return Settings::default();
+    }
+
+    std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// The yt-dlp backend and extractor options the user has configured,
+/// falling back to embedded PyO3 with default retry/timeout behavior.
+/// Installing a managed binary via `update_ytdlp` never silently switches
+/// someone off the `Embedded` default - only the embedded backend reports
+/// live progress and honors cancellation (see `ytdlp::download`'s doc
+/// comment). A user who has explicitly opted into `External` but left
+/// `executable_path` blank gets routed to the managed binary, so picking
+/// "external binary" in settings doesn't also require knowing its path.
+/// And a clean install that still has the `Embedded` default but whose
+/// Python environment has no importable `yt_dlp` module also falls back to
+/// the managed binary, rather than failing every download with no way to
+/// recover short of opening Settings.
+fn ytdlp_config() -> (YtDlpBackend, ExtractorOptions) {
+    let settings = load_settings();
+    let backend = match settings.ytdlp_backend {
+        YtDlpBackend::External { executable_path, extra_args } if executable_path.trim().is_empty() => {
+            YtDlpBackend::External {
+                executable_path: ytdlp_update::managed_binary_path().to_string_lossy().to_string(),
+                extra_args,
+            }
+        }
+        YtDlpBackend::Embedded if !ytdlp::embedded_available() => YtDlpBackend::External {
+            executable_path: ytdlp_update::managed_binary_path().to_string_lossy().to_string(),
+            extra_args: Vec::new(),
+        },
+        other => other,
+    };
+    (backend, settings.extractor_options)
+}
+
+/// A single entry discovered while expanding a playlist/album/channel URL
+#[derive(Debug, Clone)]
+struct PlaylistEntry {
+    url: String,
+    title: Option<String>,
+    /// Uploader/channel name, used as the `parse_artist_title` fallback when
+    /// the flat-extracted title isn't already in "Artist - Title" form.
     channel: Option<String>,
-    uploader: Option<String>,
-    artist: Option<String>,
-    track: Option<String>,
-    thumbnail: Option<String>,
-    duration: Option<f64>,
+    playlist_index: Option<u32>,
 }
 
-/// Fetch video metadata using yt-dlp via PyO3
-fn ytdlp_extract_info(url: &str) -> Result<YtDlpMetadata, String> {
+/// Expand a playlist/album/channel URL into its individual track URLs using
+/// yt-dlp's flat extraction (no per-video network round trip), capped at
+/// `limit` entries so a channel URL can't enqueue someone's entire catalog.
+fn ytdlp_expand_playlist(url: &str, limit: usize) -> Result<Vec<PlaylistEntry>, String> {
     init_python_env();
     Python::with_gil(|py| {
         let yt_dlp = py.import("yt_dlp").map_err(|e| format!("Failed to import yt_dlp: {}", e))?;
 
-        // Create options dict
         let opts = PyDict::new(py);
         opts.set_item("quiet", true).unwrap();
         opts.set_item("no_warnings", true).unwrap();
-        opts.set_item("extract_flat", false).unwrap();
-        opts.set_item("noplaylist", true).unwrap();
+        opts.set_item("extract_flat", "in_playlist").unwrap();
+        opts.set_item("noplaylist", false).unwrap();
+        opts.set_item("playlistend", limit).unwrap();
 
-        // Create YoutubeDL instance
         let ydl_class = yt_dlp.getattr("YoutubeDL").map_err(|e| format!("Failed to get YoutubeDL: {}", e))?;
         let ydl = ydl_class.call1((opts,)).map_err(|e| format!("Failed to create YoutubeDL: {}", e))?;
 
-        // Extract info without downloading
         let info = ydl.call_method1("extract_info", (url, false))
             .map_err(|e| format!("Failed to extract info: {}", e))?;
 
-        // Helper to extract optional string field
-        fn get_str(info: &Bound<'_, PyAny>, key: &str) -> Option<String> {
-            info.get_item(key).ok().and_then(|v| {
-                if v.is_none() { None } else { v.extract().ok() }
-            })
-        }
+        // A bare video URL has no "entries" key - treat it as a single-item playlist.
+        let entries = match info.get_item("entries") {
+            Ok(entries) if !entries.is_none() => entries,
+            _ => return Ok(vec![PlaylistEntry {
+                url: url.to_string(),
+                title: info.get_item("title").ok().and_then(|v| v.extract().ok()),
+                channel: info.get_item("channel").ok().and_then(|v| v.extract().ok())
+                    .or_else(|| info.get_item("uploader").ok().and_then(|v| v.extract().ok())),
+                playlist_index: None,
+            }]),
+        };
+
+        let mut out = Vec::new();
+        for item in entries.try_iter().map_err(|e| format!("Failed to iterate entries: {}", e))? {
+            let item = item.map_err(|e| format!("Failed to read entry: {}", e))?;
+            if item.is_none() {
+                // yt-dlp leaves a None placeholder for unavailable entries
+                continue;
+            }
+
+            let entry_url: Option<String> = item.get_item("url").ok().and_then(|v| v.extract().ok())
+                .or_else(|| item.get_item("webpage_url").ok().and_then(|v| v.extract().ok()))
+                .or_else(|| {
+                    item.get_item("id").ok().and_then(|v| v.extract::<String>().ok())
+                        .map(|id| format!("https://www.youtube.com/watch?v={}", id))
+                });
 
-        fn get_f64(info: &Bound<'_, PyAny>, key: &str) -> Option<f64> {
-            info.get_item(key).ok().and_then(|v| {
-                if v.is_none() { None } else { v.extract().ok() }
-            })
+            let Some(entry_url) = entry_url else { continue };
+
+            out.push(PlaylistEntry {
+                url: entry_url,
+                title: item.get_item("title").ok().and_then(|v| v.extract().ok()),
+                channel: item.get_item("channel").ok().and_then(|v| v.extract().ok())
+                    .or_else(|| item.get_item("uploader").ok().and_then(|v| v.extract().ok())),
+                playlist_index: item.get_item("playlist_index").ok().and_then(|v| v.extract().ok()),
+            });
+
+            if out.len() >= limit {
+                break;
+            }
         }
 
-        // Extract fields from the info dict
-        let id: String = get_str(&info, "id").ok_or("No id field")?;
-        let title: String = get_str(&info, "title").ok_or("No title field")?;
-        let channel: Option<String> = get_str(&info, "channel");
-        let uploader: Option<String> = get_str(&info, "uploader");
-        let artist: Option<String> = get_str(&info, "artist");
-        let track: Option<String> = get_str(&info, "track");
-        let thumbnail: Option<String> = get_str(&info, "thumbnail");
-        let duration: Option<f64> = get_f64(&info, "duration");
-
-        Ok(YtDlpMetadata {
-            id,
-            title,
-            channel,
-            uploader,
-            artist,
-            track,
-            thumbnail,
-            duration,
-        })
+        Ok(out)
     })
 }
 
-/// Download audio using yt-dlp via PyO3
-fn ytdlp_download(url: &str, output_path: &str) -> Result<String, String> {
-    init_python_env();
-    Python::with_gil(|py| {
-        let yt_dlp = py.import("yt_dlp").map_err(|e| format!("Failed to import yt_dlp: {}", e))?;
+/// Best-effort lyrics lookup via yt-dlp's subtitle tracks: prefers manually
+/// uploaded (`subtitles`) over auto-generated (`automatic_captions`) English
+/// captions, downloads the VTT, and converts it into synced lyric lines.
+/// Never returns an error - lyrics are optional, like album art.
+fn fetch_lyrics(url: &str) -> Option<Lyrics> {
+    let vtt_url = Python::with_gil(|py| -> Option<String> {
+        let yt_dlp = py.import("yt_dlp").ok()?;
 
-        // Create options dict
         let opts = PyDict::new(py);
-        opts.set_item("quiet", true).unwrap();
-        opts.set_item("no_warnings", true).unwrap();
-        opts.set_item("noplaylist", true).unwrap();
-        opts.set_item("format", "bestaudio[ext=m4a]/bestaudio/best").unwrap();
-        opts.set_item("outtmpl", output_path).unwrap();
-
-        // Post-processors to extract audio
-        let pp_dict = PyDict::new(py);
-        pp_dict.set_item("key", "FFmpegExtractAudio").unwrap();
-        pp_dict.set_item("preferredcodec", "m4a").unwrap();
-        let pp_list = PyList::new(py, &[pp_dict]).map_err(|e| format!("Failed to create list: {}", e))?;
-        opts.set_item("postprocessors", pp_list).unwrap();
-
-        // Create YoutubeDL instance
-        let ydl_class = yt_dlp.getattr("YoutubeDL").map_err(|e| format!("Failed to get YoutubeDL: {}", e))?;
-        let ydl = ydl_class.call1((opts,)).map_err(|e| format!("Failed to create YoutubeDL: {}", e))?;
+        opts.set_item("quiet", true).ok()?;
+        opts.set_item("no_warnings", true).ok()?;
+        opts.set_item("noplaylist", true).ok()?;
+        opts.set_item("writesubtitles", true).ok()?;
+        opts.set_item("writeautomaticsub", true).ok()?;
+        opts.set_item("subtitleslangs", vec!["en"]).ok()?;
+
+        let ydl_class = yt_dlp.getattr("YoutubeDL").ok()?;
+        let ydl = ydl_class.call1((opts,)).ok()?;
+        let info = ydl.call_method1("extract_info", (url, false)).ok()?;
+
+        for key in ["subtitles", "automatic_captions"] {
+            let Ok(tracks) = info.get_item(key) else { continue };
+            if tracks.is_none() {
+                continue;
+            }
+            let Ok(en_track) = tracks.get_item("en") else { continue };
+            let Ok(mut formats) = en_track.try_iter() else { continue };
+            let vtt = formats.find_map(|f| {
+                let f = f.ok()?;
+                let ext: String = f.get_item("ext").ok()?.extract().ok()?;
+                if ext == "vtt" {
+                    f.get_item("url").ok()?.extract().ok()
+                } else {
+                    None
+                }
+            });
+            if vtt.is_some() {
+                return vtt;
+            }
+        }
 
-        // Download
-        ydl.call_method1("download", (vec![url],))
-            .map_err(|e| format!("Failed to download: {}", e))?;
+        None
+    })?;
 
-        Ok(output_path.to_string())
-    })
+    let vtt_body = reqwest::blocking::get(&vtt_url).ok()?.text().ok()?;
+    let synced = parse_vtt_to_synced_lines(&vtt_body);
+    if synced.is_empty() {
+        return None;
+    }
+
+    let plain = synced.iter().map(|l| l.text.clone()).collect::<Vec<_>>().join("\n");
+    Some(Lyrics { synced: Some(synced), plain: Some(plain) })
+}
+
+/// Parse WebVTT cue blocks (`00:00:01.000 --> 00:00:04.000` + text lines)
+/// into timestamped lyric lines, skipping the `WEBVTT` header and empty cues.
+fn parse_vtt_to_synced_lines(vtt: &str) -> Vec<SyncedLine> {
+    fn parse_timestamp(ts: &str) -> Option<u32> {
+        let ts = ts.trim();
+        let (hms, ms) = ts.split_once('.')?;
+        let parts: Vec<&str> = hms.split(':').collect();
+        let (h, m, s) = match parts.as_slice() {
+            [h, m, s] => (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?, s.parse::<u32>().ok()?),
+            [m, s] => (0, m.parse::<u32>().ok()?, s.parse::<u32>().ok()?),
+            _ => return None,
+        };
+        let ms: u32 = ms.parse().ok()?;
+        Some(((h * 3600 + m * 60 + s) * 1000) + ms)
+    }
+
+    let mut lines = Vec::new();
+    let mut cue_start: Option<u32> = None;
+    let mut cue_text: Vec<String> = Vec::new();
+
+    for raw_line in vtt.lines() {
+        if let Some((start, _end)) = raw_line.split_once("-->") {
+            if let Some(start_ms) = parse_timestamp(start) {
+                cue_start = Some(start_ms);
+                cue_text.clear();
+            }
+            continue;
+        }
+
+        if raw_line.trim().is_empty() {
+            if let Some(start_ms) = cue_start.take() {
+                let text = cue_text.join(" ").trim().to_string();
+                if !text.is_empty() {
+                    lines.push(SyncedLine { timestamp_ms: start_ms, text });
+                }
+            }
+            cue_text.clear();
+            continue;
+        }
+
+        if cue_start.is_some() && raw_line != "WEBVTT" {
+            cue_text.push(raw_line.trim().to_string());
+        }
+    }
+
+    lines
+}
+
+/// Resolve lyrics for a track: try the dedicated lyrics provider first (it
+/// knows about time-synced LRC), falling back to yt-dlp's subtitle tracks.
+/// Writes a `.lrc` file alongside `final_path` when synced lines are found.
+/// Returns `None` outright when lyrics fetching is off in settings, and
+/// never fails the download on a lookup error - lyrics are optional, like
+/// album art.
+fn resolve_lyrics(
+    settings: &Settings,
+    url: &str,
+    artist: &str,
+    title: &str,
+    duration_secs: Option<f64>,
+    final_path: &Path,
+) -> Option<Lyrics> {
+    if !settings.fetch_lyrics {
+        return None;
+    }
+
+    let found = lyrics::fetch(artist, title, duration_secs).or_else(|| fetch_lyrics(url))?;
+
+    if let Some(synced) = &found.synced {
+        if !synced.is_empty() {
+            let lrc_path = final_path.with_extension("lrc");
+            if let Err(e) = std::fs::write(&lrc_path, lyrics::render_lrc(synced)) {
+                eprintln!("Warning: failed to write .lrc file: {}", e);
+            }
+        }
+    }
+
+    Some(found)
 }
 
 fn clean_title(title: &str) -> String {
@@ -355,154 +607,15 @@ fn parse_artist_title(raw_title: &str, channel_name: &str) -> (String, String) {
     (channel_name.to_string(), cleaned)
 }
 
-/// Convert M4A/AAC audio file to MP3 using symphonia (decoder) and mp3lame-encoder
+/// Convert a downloaded audio file to MP3 at a fixed CBR bitrate. Thin
+/// wrapper around `formats::convert_audio`; the download commands call
+/// `formats::convert_audio` directly and nothing in this crate calls it.
 fn convert_to_mp3(input_path: &Path, output_path: &Path, bitrate_kbps: u32) -> Result<(), String> {
-    // Open the input file
-    let file = File::open(input_path).map_err(|e| format!("Failed to open input file: {}", e))?;
-
-    let mss = MediaSourceStream::new(Box::new(file), Default::default());
-
-    // Create a hint to help the format registry guess the format
-    let mut hint = Hint::new();
-    if let Some(ext) = input_path.extension() {
-        hint.with_extension(ext.to_str().unwrap_or("m4a"));
-    }
-
-    // Probe the media source
-    let probed = symphonia::default::get_probe()
-        .format(
-            &hint,
-            mss,
-            &FormatOptions::default(),
-            &MetadataOptions::default(),
-        )
-        .map_err(|e| format!("Failed to probe audio format: {}", e))?;
-
-    let mut format = probed.format;
-
-    // Find the first audio track
-    let track = format
-        .tracks()
-        .iter()
-        .find(|t| t.codec_params.codec != symphonia::core::codecs::CODEC_TYPE_NULL)
-        .ok_or("No audio track found")?;
-
-    let track_id = track.id;
-
-    // Create decoder
-    let mut decoder = symphonia::default::get_codecs()
-        .make(&track.codec_params, &DecoderOptions::default())
-        .map_err(|e| format!("Failed to create decoder: {}", e))?;
-
-    // Get audio parameters
-    let sample_rate = track
-        .codec_params
-        .sample_rate
-        .ok_or("Unknown sample rate")?;
-    // Default to stereo if channel count not available (common for YouTube audio)
-    let channels = track
-        .codec_params
-        .channels
-        .map(|c| c.count())
-        .unwrap_or(2);
-
-    // Create MP3 encoder
-    let mut mp3_encoder = Builder::new().ok_or("Failed to create MP3 encoder")?;
-    mp3_encoder
-        .set_num_channels(channels as u8)
-        .map_err(|e| format!("Failed to set channels: {:?}", e))?;
-    mp3_encoder
-        .set_sample_rate(sample_rate)
-        .map_err(|e| format!("Failed to set sample rate: {:?}", e))?;
-    mp3_encoder
-        .set_brate(match bitrate_kbps {
-            128 => mp3lame_encoder::Bitrate::Kbps128,
-            192 => mp3lame_encoder::Bitrate::Kbps192,
-            256 => mp3lame_encoder::Bitrate::Kbps256,
-            320 => mp3lame_encoder::Bitrate::Kbps320,
-            _ => mp3lame_encoder::Bitrate::Kbps192,
-        })
-        .map_err(|e| format!("Failed to set bitrate: {:?}", e))?;
-    mp3_encoder
-        .set_quality(mp3lame_encoder::Quality::Best)
-        .map_err(|e| format!("Failed to set quality: {:?}", e))?;
-
-    let mut mp3_encoder = mp3_encoder
-        .build()
-        .map_err(|e| format!("Failed to build MP3 encoder: {:?}", e))?;
-
-    // Create output file
-    let mut output_file =
-        File::create(output_path).map_err(|e| format!("Failed to create output file: {}", e))?;
-
-    // Decode and encode
-    let mut sample_buf: Option<SampleBuffer<i16>> = None;
-
-    loop {
-        // Get next packet
-        let packet = match format.next_packet() {
-            Ok(packet) => packet,
-            Err(symphonia::core::errors::Error::IoError(ref e))
-                if e.kind() == std::io::ErrorKind::UnexpectedEof =>
-            {
-                break;
-            }
-            Err(e) => return Err(format!("Failed to read packet: {}", e)),
-        };
-
-        // Skip packets from other tracks
-        if packet.track_id() != track_id {
-            continue;
-        }
-
-        // Decode packet
-        let decoded = match decoder.decode(&packet) {
-            Ok(decoded) => decoded,
-            Err(symphonia::core::errors::Error::DecodeError(_)) => continue,
-            Err(e) => return Err(format!("Failed to decode: {}", e)),
-        };
-
-        // Convert to interleaved i16 samples
-        if sample_buf.is_none() {
-            let spec = *decoded.spec();
-            let duration = decoded.capacity() as u64;
-            sample_buf = Some(SampleBuffer::new(duration, spec));
-        }
-
-        if let Some(ref mut buf) = sample_buf {
-            buf.copy_interleaved_ref(decoded);
-            let samples = buf.samples();
-
-            // Encode to MP3
-            let input = InterleavedPcm(samples);
-            let buf_size = mp3lame_encoder::max_required_buffer_size(samples.len());
-            let mut mp3_out: Vec<MaybeUninit<u8>> = vec![MaybeUninit::uninit(); buf_size];
-            let encoded_size = mp3_encoder
-                .encode(input, &mut mp3_out)
-                .map_err(|e| format!("Failed to encode MP3: {:?}", e))?;
-
-            // Safety: mp3lame-encoder initializes the bytes it writes
-            let mp3_bytes: &[u8] =
-                unsafe { std::slice::from_raw_parts(mp3_out.as_ptr() as *const u8, encoded_size) };
-            output_file
-                .write_all(mp3_bytes)
-                .map_err(|e| format!("Failed to write MP3 data: {}", e))?;
-        }
-    }
-
-    // Flush the encoder
-    let mut mp3_out: Vec<MaybeUninit<u8>> = vec![MaybeUninit::uninit(); 7200];
-    let encoded_size = mp3_encoder
-        .flush::<FlushNoGap>(&mut mp3_out)
-        .map_err(|e| format!("Failed to flush MP3 encoder: {:?}", e))?;
-    // Safety: mp3lame-encoder initializes the bytes it writes
-    let mp3_bytes: &[u8] =
-        unsafe { std::slice::from_raw_parts(mp3_out.as_ptr() as *const u8, encoded_size) };
-    output_file
-        .write_all(mp3_bytes)
-        .map_err(|e| format!("Failed to write final MP3 data: {}", e))?;
-
-    Ok(())
+    formats::convert_audio(
+        input_path,
+        output_path,
+        &OutputFormat::Mp3 { mode: Mp3EncodingMode::Cbr { bitrate_kbps } },
+    )
 }
 
 fn get_settings_path() -> PathBuf {
@@ -514,8 +627,8 @@ fn get_settings_path() -> PathBuf {
 
 #[tauri::command]
 async fn fetch_metadata(url: String) -> Result<VideoMetadata, String> {
-    // Use PyO3 to call yt-dlp
-    let metadata = ytdlp_extract_info(&url)?;
+    let (backend, opts) = ytdlp_config();
+    let metadata = ytdlp::extract_info(&backend, &url, &opts)?;
 
     // Get artist and title - prefer track/artist fields if available (YouTube Music)
     let (artist, title) = if metadata.artist.is_some() && metadata.track.is_some() {
@@ -552,14 +665,70 @@ fn emit_progress(app: &tauri::AppHandle, stage: &str, percent: u8, message: &str
     });
 }
 
-/// Check if download was cancelled
+/// Map a yt-dlp progress fraction (downloaded/total, 0.0-1.0) onto a percent
+/// within the `[low, high]` band a given download stage owns.
+fn band_percent(low: u8, high: u8, fraction: f64) -> u8 {
+    low + (((high - low) as f64) * fraction.clamp(0.0, 1.0)).round() as u8
+}
+
+/// Human-readable transfer speed for a yt-dlp progress message, e.g. "2.3 MB".
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Best-effort `{source}` placeholder value, guessed from the URL alone.
+/// Spotify-resolved queue items carry their matched YouTube URL, so they
+/// render as "YouTube".
+fn source_label(url: &str) -> &'static str {
+    if url.contains("spotify.com") {
+        "Spotify"
+    } else {
+        "YouTube"
+    }
+}
+
+/// Check if download was cancelled (single-shot, non-queue flows)
 fn is_cancelled() -> bool {
     CANCEL_DOWNLOAD.load(Ordering::SeqCst)
 }
 
+/// Locate the file yt-dlp just wrote for a temp-download prefix. `ytdlp::download`
+/// no longer forces every stream through `FFmpegExtractAudio`/m4a, so the
+/// actual extension depends on whatever container/codec the source served -
+/// `prefix` (the filename with its placeholder extension stripped) is all we
+/// can match on.
+fn find_downloaded_file(output_dir: &str, prefix: &str) -> Result<PathBuf, String> {
+    std::fs::read_dir(output_dir)
+        .map_err(|e| format!("Failed to read output dir: {}", e))?
+        .filter_map(|e| e.ok())
+        .find(|e| e.file_name().to_string_lossy().starts_with(prefix))
+        .map(|e| e.path())
+        .ok_or_else(|| "Downloaded file not found".to_string())
+}
+
+/// Cancel a download. With no `id`, cancels the single in-flight non-queue
+/// download (`download_audio`/`download_audio_trimmed`); with an `id`, cancels
+/// just that queue item, leaving the rest of the worker pool running.
 #[tauri::command]
-async fn cancel_download() -> Result<(), String> {
-    CANCEL_DOWNLOAD.store(true, Ordering::SeqCst);
+async fn cancel_download(id: Option<String>) -> Result<(), String> {
+    match id {
+        Some(id) => {
+            let control = DOWNLOAD_CONTROL.lock().map_err(|e| format!("Control lock error: {}", e))?;
+            if let Some(flag) = control.get(&id) {
+                flag.store(true, Ordering::SeqCst);
+            }
+        }
+        None => {
+            CANCEL_DOWNLOAD.store(true, Ordering::SeqCst);
+        }
+    }
     Ok(())
 }
 
@@ -575,7 +744,7 @@ fn emit_queue_progress(app: &tauri::AppHandle, id: &str, status: QueueStatus, pr
 
 /// Add a URL to the download queue
 #[tauri::command]
-async fn add_to_queue(app: tauri::AppHandle, url: String) -> Result<QueueItem, String> {
+async fn add_to_queue(app: tauri::AppHandle, url: String, format: Option<OutputFormat>) -> Result<QueueItem, String> {
     let id = Uuid::new_v4().to_string()[..8].to_string();
 
     let item = QueueItem {
@@ -589,6 +758,8 @@ async fn add_to_queue(app: tauri::AppHandle, url: String) -> Result<QueueItem, S
         progress: 0,
         error: None,
         output_path: None,
+        format,
+        playlist_index: None,
     };
 
     {
@@ -626,6 +797,8 @@ async fn add_urls_to_queue(app: tauri::AppHandle, urls: Vec<String>) -> Result<V
             progress: 0,
             error: None,
             output_path: None,
+            format: None,
+            playlist_index: None,
         };
 
         {
@@ -642,6 +815,116 @@ async fn add_urls_to_queue(app: tauri::AppHandle, urls: Vec<String>) -> Result<V
     Ok(items)
 }
 
+/// Expand a playlist/album/channel URL and add every track to the queue in
+/// one shot, so pasting a single link bulk-imports the whole thing.
+#[tauri::command]
+async fn add_playlist_to_queue(app: tauri::AppHandle, url: String, limit: Option<usize>) -> Result<Vec<QueueItem>, String> {
+    let limit = limit.unwrap_or(1000);
+    let entries = ytdlp_expand_playlist(&url, limit)?;
+
+    let mut items = Vec::new();
+    {
+        let mut queue = DOWNLOAD_QUEUE.lock().map_err(|e| format!("Queue lock error: {}", e))?;
+        for entry in entries {
+            let id = Uuid::new_v4().to_string()[..8].to_string();
+
+            // Parse artist/title up front so `process_queue_item`'s
+            // already-have-metadata fast path can skip a redundant
+            // per-video fetch, same as the Spotify-matched queue path.
+            let (title, artist) = match &entry.title {
+                Some(raw_title) => {
+                    let channel = entry.channel.clone().unwrap_or_else(|| "Unknown Artist".to_string());
+                    let (artist, title) = parse_artist_title(raw_title, &channel);
+                    (Some(title), Some(artist))
+                }
+                None => (None, None),
+            };
+
+            let item = QueueItem {
+                id: id.clone(),
+                url: entry.url,
+                title,
+                artist,
+                thumbnail: None,
+                duration: None,
+                status: QueueStatus::Pending,
+                progress: 0,
+                error: None,
+                output_path: None,
+                format: None,
+                playlist_index: entry.playlist_index,
+            };
+
+            queue.insert(id, item.clone());
+            items.push(item);
+        }
+    }
+
+    // One event for the whole batch, not one per entry.
+    let _ = app.emit("queue-updated", get_queue_items()?);
+
+    Ok(items)
+}
+
+/// Resolve a Spotify track/album/playlist URL, match each track to a YouTube
+/// video via `ytdlp::search`, and add the matches to the queue. Since Spotify
+/// doesn't serve audio, the queue item's URL points at the matched YouTube
+/// video while `title`/`artist` are pre-filled from Spotify so the queue
+/// worker (`process_queue_item`) skips its own video-title guess.
+#[tauri::command]
+async fn add_spotify_to_queue(app: tauri::AppHandle, url: String, format: Option<OutputFormat>) -> Result<Vec<QueueItem>, String> {
+    let settings = get_settings().await?;
+    let client_id = settings
+        .spotify_client_id
+        .as_deref()
+        .ok_or("Spotify client ID is not configured")?;
+    let client_secret = settings
+        .spotify_client_secret
+        .as_deref()
+        .ok_or("Spotify client secret is not configured")?;
+
+    let tracks = spotify::resolve(&url, client_id, client_secret)?;
+    let (backend, opts) = ytdlp_config();
+
+    let mut items = Vec::new();
+    for track in tracks {
+        let query = format!("{} {}", track.artist, track.title);
+        let candidates = match ytdlp::search(&backend, &query, 5, &opts) {
+            Ok(candidates) => candidates,
+            Err(_) => continue,
+        };
+        let Some(matched) = spotify::best_match(&track, &candidates) else {
+            continue;
+        };
+
+        let id = Uuid::new_v4().to_string()[..8].to_string();
+        let item = QueueItem {
+            id: id.clone(),
+            url: format!("https://www.youtube.com/watch?v={}", matched.id),
+            title: Some(track.title),
+            artist: Some(track.artist),
+            thumbnail: track.cover_url.or_else(|| matched.thumbnail.clone()),
+            duration: matched.duration.map(|d| d as u64),
+            status: QueueStatus::Pending,
+            progress: 0,
+            error: None,
+            output_path: None,
+            format,
+            playlist_index: None,
+        };
+
+        {
+            let mut queue = DOWNLOAD_QUEUE.lock().map_err(|e| format!("Queue lock error: {}", e))?;
+            queue.insert(id, item.clone());
+        }
+        items.push(item);
+    }
+
+    let _ = app.emit("queue-updated", get_queue_items()?);
+
+    Ok(items)
+}
+
 /// Get all items in the queue
 fn get_queue_items() -> Result<Vec<QueueItem>, String> {
     let queue = DOWNLOAD_QUEUE.lock().map_err(|e| format!("Queue lock error: {}", e))?;
@@ -706,6 +989,42 @@ fn update_queue_item_status(id: &str, status: QueueStatus, progress: u8, error:
     Ok(())
 }
 
+/// Drain every `Pending`/`Ready` item in the queue through a bounded worker
+/// pool instead of one item at a time. Uses the settings download directory
+/// for every item, same as the previous one-at-a-time flow.
+#[tauri::command]
+async fn start_queue(app: tauri::AppHandle, concurrency: Option<usize>) -> Result<(), String> {
+    use futures::stream::{self, StreamExt};
+
+    let settings = get_settings().await?;
+    let concurrency = concurrency.unwrap_or(settings.queue_concurrency).max(1);
+    let output_dir = settings.download_dir;
+
+    let ids: Vec<String> = {
+        let queue = DOWNLOAD_QUEUE.lock().map_err(|e| format!("Queue lock error: {}", e))?;
+        queue
+            .values()
+            .filter(|item| matches!(item.status, QueueStatus::Pending | QueueStatus::Ready))
+            .map(|item| item.id.clone())
+            .collect()
+    };
+
+    stream::iter(ids.into_iter().map(|id| {
+        let app = app.clone();
+        let output_dir = output_dir.clone();
+        async move {
+            if let Err(e) = process_queue_item(app, id.clone(), output_dir).await {
+                eprintln!("Warning: queue item {} failed: {}", id, e);
+            }
+        }
+    }))
+    .buffer_unordered(concurrency)
+    .collect::<Vec<_>>()
+    .await;
+
+    Ok(())
+}
+
 /// Process a single queue item (fetch metadata + download)
 #[tauri::command]
 async fn process_queue_item(
@@ -719,6 +1038,8 @@ async fn process_queue_item(
         queue.get(&id).cloned().ok_or("Item not found in queue")?
     };
 
+    let cancel_flag = register_cancellation(&id);
+
     // Update status to fetching
     update_queue_item_status(&id, QueueStatus::Fetching, 0, None, None)?;
     emit_queue_progress(&app, &id, QueueStatus::Fetching, 0, "Fetching metadata...");
@@ -728,7 +1049,8 @@ async fn process_queue_item(
     let (title, artist, thumbnail) = if item.title.is_some() && item.artist.is_some() {
         (item.title.unwrap(), item.artist.unwrap(), item.thumbnail)
     } else {
-        let metadata = ytdlp_extract_info(&item.url).map_err(|e| {
+        let (backend, opts) = ytdlp_config();
+        let metadata = ytdlp::extract_info(&backend, &item.url, &opts).map_err(|e| {
             let _ = update_queue_item_status(&id, QueueStatus::Failed, 0, Some(e.clone()), None);
             let _ = app.emit("queue-updated", get_queue_items().unwrap_or_default());
             e
@@ -754,8 +1076,24 @@ async fn process_queue_item(
     emit_queue_progress(&app, &id, QueueStatus::Downloading, 10, "Starting download...");
     let _ = app.emit("queue-updated", get_queue_items()?);
 
+    // Per-item format override falls back to the user's quality preset
+    let format = item.format.clone().unwrap_or_else(|| load_settings().quality_preset.to_output_format());
+
     // Download the audio (using the existing download logic)
-    let result = download_audio_internal(&app, &id, &item.url, &title, &artist, &output_dir, thumbnail.as_deref()).await;
+    let result = download_audio_internal(
+        &app,
+        &id,
+        &cancel_flag,
+        &item.url,
+        &title,
+        &artist,
+        &output_dir,
+        thumbnail.as_deref(),
+        &format,
+        item.playlist_index,
+    )
+    .await;
+    unregister_cancellation(&id);
 
     match result {
         Ok(download_result) => {
@@ -777,11 +1115,14 @@ async fn process_queue_item(
 async fn download_audio_internal(
     app: &tauri::AppHandle,
     queue_id: &str,
+    cancel_flag: &Arc<AtomicBool>,
     url: &str,
     title: &str,
     artist: &str,
     output_dir: &str,
     thumbnail_url: Option<&str>,
+    format: &OutputFormat,
+    playlist_index: Option<u32>,
 ) -> Result<DownloadResult, String> {
     // Sanitize filename
     let safe_title: String = title
@@ -800,20 +1141,26 @@ async fn download_audio_internal(
         })
         .collect();
 
-    // Final MP3 path
-    let final_filename = format!("{} - {}.mp3", safe_artist, safe_title);
-    let final_path = PathBuf::from(output_dir).join(&final_filename);
-
-    // If the file already exists, return it immediately
-    if final_path.exists() {
-        return Ok(DownloadResult {
-            success: true,
-            path: final_path.to_string_lossy().to_string(),
-        });
-    }
+    // Final output path, rendered from the user's filename/folder template.
+    // Album/year aren't known yet at this point in the queue flow (only
+    // enrichment, which runs post-download, can supply them), and
+    // `playlist_index` only feeds the track-number tag below, so the template
+    // renders the album/track/year placeholders empty.
+    let fields = template::TemplateFields {
+        artist,
+        title,
+        album: None,
+        track_number: None,
+        year: None,
+        source: source_label(url),
+    };
+    let settings = load_settings();
+    let rendered = template::render(&settings.filename_template, &fields, format.extension());
+    let final_path = template::resolve_path(Path::new(output_dir), &rendered)
+        .map_err(|e| DownloadError::FileError(e).to_string())?;
 
     // Check for cancellation
-    if is_cancelled() {
+    if cancel_flag.load(Ordering::SeqCst) {
         return Err(DownloadError::Cancelled.to_string());
     }
 
@@ -821,16 +1168,42 @@ async fn download_audio_internal(
     emit_queue_progress(app, queue_id, QueueStatus::Downloading, 20, "Fetching audio stream...");
     let _ = update_queue_item_status(queue_id, QueueStatus::Downloading, 20, None, None);
 
-    // Download as m4a first
-    let temp_filename = format!("{} - {}.m4a", safe_artist, safe_title);
-    let temp_path = PathBuf::from(output_dir).join(&temp_filename);
-
-    ytdlp_download(url, temp_path.to_str().unwrap())
+    // Download whatever container/codec yt-dlp natively serves (no forced
+    // m4a re-encode - see `ytdlp::download`), so the real extension isn't
+    // known until after the download. The queue now runs several downloads
+    // concurrently (`buffer_unordered`), so the prefix needs a
+    // per-item-unique component - two queued items for the same
+    // artist/title would otherwise clobber each other's in-progress file.
+    let temp_prefix = format!("{} - {}.{}", safe_artist, safe_title, queue_id);
+    let temp_path = PathBuf::from(output_dir).join(format!("{}.download", temp_prefix));
+
+    let (backend, opts) = ytdlp_config();
+    let progress_app = app.clone();
+    let progress_queue_id = queue_id.to_string();
+    let on_progress: ProgressHook = Arc::new(move |progress: ytdlp::DownloadProgress| {
+        let fraction = progress
+            .total_bytes
+            .filter(|&total| total > 0)
+            .map(|total| progress.downloaded_bytes as f64 / total as f64)
+            .unwrap_or(0.0);
+        let percent = band_percent(10, 60, fraction);
+        let message = match progress.speed {
+            Some(speed) => format!("Downloading... {}/s", format_bytes(speed as u64)),
+            None => "Downloading...".to_string(),
+        };
+        emit_queue_progress(&progress_app, &progress_queue_id, QueueStatus::Downloading, percent, &message);
+        let _ = update_queue_item_status(&progress_queue_id, QueueStatus::Downloading, percent, None, None);
+    });
+    let cancel_flag_check = cancel_flag.clone();
+    let is_cancelled_cb: CancelCheck = Arc::new(move || cancel_flag_check.load(Ordering::SeqCst));
+    ytdlp::download(&backend, url, temp_path.to_str().unwrap(), &opts, Some(on_progress), Some(is_cancelled_cb))
         .map_err(|e| {
             if e.contains("URL") || e.contains("Unsupported") {
                 DownloadError::InvalidUrl(e).to_string()
             } else if e.contains("network") || e.contains("connection") || e.contains("timeout") {
                 DownloadError::NetworkError(e).to_string()
+            } else if e.contains("cancelled") || e.contains("Cancelled") {
+                DownloadError::Cancelled.to_string()
             } else {
                 DownloadError::Unknown(e).to_string()
             }
@@ -840,40 +1213,22 @@ async fn download_audio_internal(
     let _ = update_queue_item_status(queue_id, QueueStatus::Downloading, 60, None, None);
 
     // Check for cancellation
-    if is_cancelled() {
-        let _ = std::fs::remove_file(&temp_path);
+    if cancel_flag.load(Ordering::SeqCst) {
+        if let Ok(partial) = find_downloaded_file(output_dir, &temp_prefix) {
+            let _ = std::fs::remove_file(partial);
+        }
         return Err(DownloadError::Cancelled.to_string());
     }
 
     // Find the downloaded file
-    let actual_temp_path = if temp_path.exists() {
-        temp_path.clone()
-    } else {
-        let with_ext = PathBuf::from(format!("{}.m4a", temp_path.display()));
-        if with_ext.exists() {
-            with_ext
-        } else {
-            let entries: Vec<_> = std::fs::read_dir(output_dir)
-                .map_err(|e| DownloadError::FileError(format!("Failed to read output dir: {}", e)).to_string())?
-                .filter_map(|e| e.ok())
-                .filter(|e| {
-                    e.file_name()
-                        .to_string_lossy()
-                        .starts_with(&format!("{} - {}", safe_artist, safe_title))
-                })
-                .collect();
-
-            entries.first()
-                .map(|e| e.path())
-                .ok_or_else(|| DownloadError::FileError("Downloaded file not found".to_string()).to_string())?
-        }
-    };
+    let actual_temp_path = find_downloaded_file(output_dir, &temp_prefix)
+        .map_err(|e| DownloadError::FileError(e).to_string())?;
 
     // Stage 2: Converting (60-90%)
-    emit_queue_progress(app, queue_id, QueueStatus::Downloading, 65, "Converting to MP3...");
+    emit_queue_progress(app, queue_id, QueueStatus::Downloading, 65, &format!("Converting to {}...", format.extension().to_uppercase()));
     let _ = update_queue_item_status(queue_id, QueueStatus::Downloading, 65, None, None);
 
-    convert_to_mp3(&actual_temp_path, &final_path, 192)
+    formats::convert_audio(&actual_temp_path, &final_path, format)
         .map_err(|e| DownloadError::ConversionError(e).to_string())?;
 
     emit_queue_progress(app, queue_id, QueueStatus::Downloading, 90, "Conversion complete");
@@ -882,39 +1237,61 @@ async fn download_audio_internal(
     // Stage 3: Tagging (90-100%)
     emit_queue_progress(app, queue_id, QueueStatus::Downloading, 92, "Writing metadata...");
 
-    let mut tag = id3::Tag::new();
-    tag.set_title(title);
-    tag.set_artist(artist);
-
-    // Embed album art if thumbnail URL provided
-    if let Some(thumb_url) = thumbnail_url {
+    let thumbnail_bytes = thumbnail_url.and_then(|thumb_url| {
         emit_queue_progress(app, queue_id, QueueStatus::Downloading, 94, "Downloading album art...");
-        if let Ok(image_data) = download_thumbnail(thumb_url) {
-            let mime_type = if thumb_url.contains(".png") { "image/png" } else { "image/jpeg" };
-            let picture = id3::frame::Picture {
-                mime_type: mime_type.to_string(),
-                picture_type: id3::frame::PictureType::CoverFront,
-                description: "Cover".to_string(),
-                data: image_data,
-            };
-            tag.add_frame(picture);
+        download_thumbnail(thumb_url).ok()
+    });
+
+    let settings = load_settings();
+    let lyrics = resolve_lyrics(&settings, url, artist, title, None, &final_path);
+
+    let mut tag_title = title.to_string();
+    let mut tag_artist = artist.to_string();
+    let mut tag_album = None;
+    let mut tag_year = None;
+    // Falls back to the source playlist/album position; enrichment below
+    // overrides it with MusicBrainz's track number when that's available.
+    let mut tag_track_number = playlist_index;
+    if settings.enrich_metadata {
+        if let Some(api_key) = &settings.acoustid_api_key {
+            if let Some(enriched) = enrichment::enrich(url, &final_path, api_key) {
+                tag_title = enriched.title;
+                tag_artist = enriched.artist;
+                tag_album = enriched.album;
+                tag_year = enriched.release_year;
+                tag_track_number = enriched.track_number.or(tag_track_number);
+            }
         }
     }
 
-    tag.write_to_path(&final_path, id3::Version::Id3v24)
-        .map_err(|e| DownloadError::FileError(format!("Failed to write ID3 tags: {}", e)).to_string())?;
+    tagging::write_metadata(
+        &final_path,
+        format,
+        &TrackTags {
+            title: &tag_title,
+            artist: &tag_artist,
+            album: tag_album.as_deref(),
+            duration_ms: None,
+            year: tag_year,
+            track_number: tag_track_number,
+            genre: None,
+        },
+        thumbnail_bytes.as_deref(),
+        lyrics.as_ref(),
+    )
+    .map_err(|e| DownloadError::FileError(e).to_string())?;
 
     emit_queue_progress(app, queue_id, QueueStatus::Downloading, 98, "Cleaning up...");
 
-    // Remove the temporary m4a file
+    // Remove the temporary download file
     let _ = std::fs::remove_file(&actual_temp_path);
 
     // Save to download history
     let final_path_str = final_path.to_string_lossy().to_string();
     if let Err(e) = db::save_download(
         url,
-        title,
-        artist,
+        &tag_title,
+        &tag_artist,
         thumbnail_url,
         None, // duration could be passed in but kept simple
         &final_path_str,
@@ -950,10 +1327,13 @@ async fn download_audio(
     artist: String,
     output_dir: String,
     thumbnail_url: Option<String>,
+    format: Option<OutputFormat>,
 ) -> Result<DownloadResult, String> {
     // Reset cancellation flag at start
     CANCEL_DOWNLOAD.store(false, Ordering::SeqCst);
 
+    let format = format.unwrap_or_else(|| load_settings().quality_preset.to_output_format());
+
     // Sanitize filename
     let safe_title: String = title
         .chars()
@@ -971,18 +1351,18 @@ async fn download_audio(
         })
         .collect();
 
-    // Final MP3 path
-    let final_filename = format!("{} - {}.mp3", safe_artist, safe_title);
-    let final_path = PathBuf::from(&output_dir).join(&final_filename);
-
-    // If the file already exists, return it immediately (handle duplicates gracefully)
-    if final_path.exists() {
-        emit_progress(&app, "complete", 100, "File already exists");
-        return Ok(DownloadResult {
-            success: true,
-            path: final_path.to_string_lossy().to_string(),
-        });
-    }
+    // Final output path, rendered from the user's filename/folder template.
+    let fields = template::TemplateFields {
+        artist: &artist,
+        title: &title,
+        album: None,
+        track_number: None,
+        year: None,
+        source: source_label(&url),
+    };
+    let rendered = template::render(&load_settings().filename_template, &fields, format.extension());
+    let final_path = template::resolve_path(Path::new(&output_dir), &rendered)
+        .map_err(|e| DownloadError::FileError(e).to_string())?;
 
     // Check for cancellation
     if is_cancelled() {
@@ -992,19 +1372,40 @@ async fn download_audio(
     // Stage 1: Downloading (0-60%)
     emit_progress(&app, "downloading", 0, "Starting download...");
 
-    // Download as m4a first (best audio quality)
-    let temp_filename = format!("{} - {}.m4a", safe_artist, safe_title);
-    let temp_path = PathBuf::from(&output_dir).join(&temp_filename);
+    // Download whatever container/codec yt-dlp natively serves (no forced
+    // m4a re-encode - see `ytdlp::download`); the real extension isn't known
+    // until after the download, so `temp_path` only fixes the prefix. The
+    // `_temp` suffix keeps that prefix from also matching the output file
+    // `resolve_path` has already reserved under the same artist/title.
+    let temp_prefix = format!("{} - {}_temp", safe_artist, safe_title);
+    let temp_path = PathBuf::from(&output_dir).join(format!("{}.download", temp_prefix));
 
     emit_progress(&app, "downloading", 10, "Fetching audio stream...");
 
-    // Download audio using yt-dlp via PyO3
-    ytdlp_download(&url, temp_path.to_str().unwrap())
+    let (backend, opts) = ytdlp_config();
+    let progress_app = app.clone();
+    let on_progress: ProgressHook = Arc::new(move |progress: ytdlp::DownloadProgress| {
+        let fraction = progress
+            .total_bytes
+            .filter(|&total| total > 0)
+            .map(|total| progress.downloaded_bytes as f64 / total as f64)
+            .unwrap_or(0.0);
+        let percent = band_percent(10, 60, fraction);
+        let message = match progress.speed {
+            Some(speed) => format!("Downloading... {}/s", format_bytes(speed as u64)),
+            None => "Downloading...".to_string(),
+        };
+        emit_progress(&progress_app, "downloading", percent, &message);
+    });
+    let is_cancelled_cb: CancelCheck = Arc::new(is_cancelled);
+    ytdlp::download(&backend, &url, temp_path.to_str().unwrap(), &opts, Some(on_progress), Some(is_cancelled_cb))
         .map_err(|e| {
             if e.contains("URL") || e.contains("Unsupported") {
                 DownloadError::InvalidUrl(e).to_string()
             } else if e.contains("network") || e.contains("connection") || e.contains("timeout") {
                 DownloadError::NetworkError(e).to_string()
+            } else if e.contains("cancelled") || e.contains("Cancelled") {
+                DownloadError::Cancelled.to_string()
             } else {
                 DownloadError::Unknown(e).to_string()
             }
@@ -1015,37 +1416,18 @@ async fn download_audio(
     // Check for cancellation
     if is_cancelled() {
         // Clean up temp file
-        let _ = std::fs::remove_file(&temp_path);
+        if let Ok(partial) = find_downloaded_file(&output_dir, &temp_prefix) {
+            let _ = std::fs::remove_file(partial);
+        }
         return Err(DownloadError::Cancelled.to_string());
     }
 
     // Find the downloaded file
-    let actual_temp_path = if temp_path.exists() {
-        temp_path.clone()
-    } else {
-        let with_ext = PathBuf::from(format!("{}.m4a", temp_path.display()));
-        if with_ext.exists() {
-            with_ext
-        } else {
-            // Look for any file matching the pattern
-            let entries: Vec<_> = std::fs::read_dir(&output_dir)
-                .map_err(|e| DownloadError::FileError(format!("Failed to read output dir: {}", e)).to_string())?
-                .filter_map(|e| e.ok())
-                .filter(|e| {
-                    e.file_name()
-                        .to_string_lossy()
-                        .starts_with(&format!("{} - {}", safe_artist, safe_title))
-                })
-                .collect();
-
-            entries.first()
-                .map(|e| e.path())
-                .ok_or_else(|| DownloadError::FileError("Downloaded file not found".to_string()).to_string())?
-        }
-    };
+    let actual_temp_path =
+        find_downloaded_file(&output_dir, &temp_prefix).map_err(|e| DownloadError::FileError(e).to_string())?;
 
     // Stage 2: Converting (60-90%)
-    emit_progress(&app, "converting", 65, "Converting to MP3...");
+    emit_progress(&app, "converting", 65, &format!("Converting to {}...", format.extension().to_uppercase()));
 
     // Check for cancellation
     if is_cancelled() {
@@ -1053,7 +1435,7 @@ async fn download_audio(
         return Err(DownloadError::Cancelled.to_string());
     }
 
-    convert_to_mp3(&actual_temp_path, &final_path, 192)
+    formats::convert_audio(&actual_temp_path, &final_path, &format)
         .map_err(|e| DownloadError::ConversionError(e).to_string())?;
 
     emit_progress(&app, "converting", 90, "Conversion complete");
@@ -1061,53 +1443,73 @@ async fn download_audio(
     // Stage 3: Tagging (90-100%)
     emit_progress(&app, "tagging", 92, "Writing metadata...");
 
-    // Write ID3 tags
-    let mut tag = id3::Tag::new();
-    tag.set_title(&title);
-    tag.set_artist(&artist);
-
     // Embed album art if thumbnail URL provided
-    if let Some(ref thumb_url) = thumbnail_url {
-        emit_progress(&app, "tagging", 94, "Downloading album art...");
-        match download_thumbnail(thumb_url) {
-            Ok(image_data) => {
-                // Determine MIME type based on URL or default to JPEG
-                let mime_type = if thumb_url.contains(".png") {
-                    "image/png"
-                } else {
-                    "image/jpeg"
-                };
-
-                let picture = id3::frame::Picture {
-                    mime_type: mime_type.to_string(),
-                    picture_type: id3::frame::PictureType::CoverFront,
-                    description: "Cover".to_string(),
-                    data: image_data,
-                };
-                tag.add_frame(picture);
-                emit_progress(&app, "tagging", 96, "Album art embedded");
+    let thumbnail_bytes = match &thumbnail_url {
+        Some(thumb_url) => {
+            emit_progress(&app, "tagging", 94, "Downloading album art...");
+            match download_thumbnail(thumb_url) {
+                Ok(data) => {
+                    emit_progress(&app, "tagging", 96, "Album art embedded");
+                    Some(data)
+                }
+                Err(e) => {
+                    // Log but don't fail - album art is optional
+                    eprintln!("Warning: Failed to embed album art: {}", e);
+                    None
+                }
             }
-            Err(e) => {
-                // Log but don't fail - album art is optional
-                eprintln!("Warning: Failed to embed album art: {}", e);
+        }
+        None => None,
+    };
+
+    let settings = load_settings();
+    let lyrics = resolve_lyrics(&settings, &url, &artist, &title, None, &final_path);
+
+    let mut tag_title = title.clone();
+    let mut tag_artist = artist.clone();
+    let mut tag_album = None;
+    let mut tag_year = None;
+    let mut tag_track_number = None;
+    if settings.enrich_metadata {
+        if let Some(api_key) = &settings.acoustid_api_key {
+            if let Some(enriched) = enrichment::enrich(&url, &final_path, api_key) {
+                tag_title = enriched.title;
+                tag_artist = enriched.artist;
+                tag_album = enriched.album;
+                tag_year = enriched.release_year;
+                tag_track_number = enriched.track_number;
             }
         }
     }
 
-    tag.write_to_path(&final_path, id3::Version::Id3v24)
-        .map_err(|e| DownloadError::FileError(format!("Failed to write ID3 tags: {}", e)).to_string())?;
+    tagging::write_metadata(
+        &final_path,
+        &format,
+        &TrackTags {
+            title: &tag_title,
+            artist: &tag_artist,
+            album: tag_album.as_deref(),
+            duration_ms: None,
+            year: tag_year,
+            track_number: tag_track_number,
+            genre: None,
+        },
+        thumbnail_bytes.as_deref(),
+        lyrics.as_ref(),
+    )
+    .map_err(|e| DownloadError::FileError(e).to_string())?;
 
     emit_progress(&app, "tagging", 98, "Cleaning up...");
 
-    // Remove the temporary m4a file
+    // Remove the temporary download file
     let _ = std::fs::remove_file(&actual_temp_path);
 
     // Save to download history
     let final_path_str = final_path.to_string_lossy().to_string();
     if let Err(e) = db::save_download(
         &url,
-        &title,
-        &artist,
+        &tag_title,
+        &tag_artist,
         thumbnail_url.as_deref(),
         None, // duration not available here
         &final_path_str,
@@ -1125,14 +1527,7 @@ async fn download_audio(
 
 #[tauri::command]
 async fn get_settings() -> Result<Settings, String> {
-    let path = get_settings_path();
-
-    if path.exists() {
-        let content = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
-        serde_json::from_str(&content).map_err(|e| e.to_string())
-    } else {
-        Ok(Settings::default())
-    }
+    Ok(load_settings())
 }
 
 #[tauri::command]
@@ -1210,10 +1605,20 @@ async fn download_audio_trimmed(
     thumbnail_url: Option<String>,
     start_time: f64,
     end_time: f64,
+    format: Option<OutputFormat>,
+    encoding_mode: Option<Mp3EncodingMode>,
 ) -> Result<DownloadResult, String> {
     // Reset cancellation flag at start
     CANCEL_DOWNLOAD.store(false, Ordering::SeqCst);
 
+    let settings = load_settings();
+    let mut format = format.unwrap_or_else(|| settings.quality_preset.to_output_format());
+    // `encoding_mode` (falling back to the settings default) overrides only
+    // the MP3 bitrate strategy, leaving an explicit non-MP3 `format` alone.
+    if let OutputFormat::Mp3 { mode } = &mut format {
+        *mode = encoding_mode.unwrap_or(settings.mp3_encoding_mode);
+    }
+
     // Sanitize filename with time range indicator
     let safe_title: String = title
         .chars()
@@ -1231,19 +1636,21 @@ async fn download_audio_trimmed(
         })
         .collect();
 
-    // Include time range in filename to distinguish clips
+    // Include time range in the title placeholder to distinguish clips, then
+    // render through the user's filename/folder template as usual.
     let time_suffix = format!("_{:.0}-{:.0}s", start_time, end_time);
-    let final_filename = format!("{} - {}{}.mp3", safe_artist, safe_title, time_suffix);
-    let final_path = PathBuf::from(&output_dir).join(&final_filename);
-
-    // If the file already exists, return it immediately
-    if final_path.exists() {
-        emit_progress(&app, "complete", 100, "File already exists");
-        return Ok(DownloadResult {
-            success: true,
-            path: final_path.to_string_lossy().to_string(),
-        });
-    }
+    let clip_title = format!("{}{}", title, time_suffix);
+    let fields = template::TemplateFields {
+        artist: &artist,
+        title: &clip_title,
+        album: None,
+        track_number: None,
+        year: None,
+        source: source_label(&url),
+    };
+    let rendered = template::render(&load_settings().filename_template, &fields, format.extension());
+    let final_path = template::resolve_path(Path::new(&output_dir), &rendered)
+        .map_err(|e| DownloadError::FileError(e).to_string())?;
 
     // Check for cancellation
     if is_cancelled() {
@@ -1253,18 +1660,36 @@ async fn download_audio_trimmed(
     // Stage 1: Downloading (0-50%)
     emit_progress(&app, "downloading", 0, "Starting download...");
 
-    // Download as m4a first
-    let temp_filename = format!("{} - {}_temp.m4a", safe_artist, safe_title);
-    let temp_path = PathBuf::from(&output_dir).join(&temp_filename);
+    // Download to a temp file first, then trim/convert into place below.
+    let temp_prefix = format!("{} - {}_temp", safe_artist, safe_title);
+    let temp_path = PathBuf::from(&output_dir).join(format!("{}.download", temp_prefix));
 
     emit_progress(&app, "downloading", 10, "Fetching audio stream...");
 
-    ytdlp_download(&url, temp_path.to_str().unwrap())
+    let (backend, opts) = ytdlp_config();
+    let progress_app = app.clone();
+    let on_progress: ProgressHook = Arc::new(move |progress: ytdlp::DownloadProgress| {
+        let fraction = progress
+            .total_bytes
+            .filter(|&total| total > 0)
+            .map(|total| progress.downloaded_bytes as f64 / total as f64)
+            .unwrap_or(0.0);
+        let percent = band_percent(10, 50, fraction);
+        let message = match progress.speed {
+            Some(speed) => format!("Downloading... {}/s", format_bytes(speed as u64)),
+            None => "Downloading...".to_string(),
+        };
+        emit_progress(&progress_app, "downloading", percent, &message);
+    });
+    let is_cancelled_cb: CancelCheck = Arc::new(is_cancelled);
+    ytdlp::download(&backend, &url, temp_path.to_str().unwrap(), &opts, Some(on_progress), Some(is_cancelled_cb))
         .map_err(|e| {
             if e.contains("URL") || e.contains("Unsupported") {
                 DownloadError::InvalidUrl(e).to_string()
             } else if e.contains("network") || e.contains("connection") || e.contains("timeout") {
                 DownloadError::NetworkError(e).to_string()
+            } else if e.contains("cancelled") || e.contains("Cancelled") {
+                DownloadError::Cancelled.to_string()
             } else {
                 DownloadError::Unknown(e).to_string()
             }
@@ -1274,70 +1699,80 @@ async fn download_audio_trimmed(
 
     // Check for cancellation
     if is_cancelled() {
-        let _ = std::fs::remove_file(&temp_path);
+        if let Ok(partial) = find_downloaded_file(&output_dir, &temp_prefix) {
+            let _ = std::fs::remove_file(partial);
+        }
         return Err(DownloadError::Cancelled.to_string());
     }
 
     // Find the downloaded file
-    let actual_temp_path = if temp_path.exists() {
-        temp_path.clone()
-    } else {
-        let with_ext = PathBuf::from(format!("{}.m4a", temp_path.display()));
-        if with_ext.exists() {
-            with_ext
-        } else {
-            let entries: Vec<_> = std::fs::read_dir(&output_dir)
-                .map_err(|e| DownloadError::FileError(format!("Failed to read output dir: {}", e)).to_string())?
-                .filter_map(|e| e.ok())
-                .filter(|e| {
-                    e.file_name()
-                        .to_string_lossy()
-                        .contains(&format!("{} - {}_temp", safe_artist, safe_title))
-                })
-                .collect();
-
-            entries.first()
-                .map(|e| e.path())
-                .ok_or_else(|| DownloadError::FileError("Downloaded file not found".to_string()).to_string())?
+    let actual_temp_path = find_downloaded_file(&output_dir, &temp_prefix)
+        .map_err(|e| DownloadError::FileError(e).to_string())?;
+
+    // Stage 2: Converting with trim (50-90%). The decode/encode loop is
+    // CPU-bound, so it runs on a blocking thread rather than the async
+    // runtime that's also emitting progress - otherwise a long clip would
+    // freeze the whole command for the duration of the conversion. Progress
+    // comes back over an mpsc channel that a second blocking task forwards
+    // as `download-progress` events, same as the live download progress
+    // above.
+    emit_progress(&app, "converting", 55, &format!("Converting and trimming to {}...", format.extension()));
+
+    let (progress_tx, progress_rx) = std::sync::mpsc::channel::<TrimProgress>();
+    let progress_app = app.clone();
+    let progress_task = tauri::async_runtime::spawn_blocking(move || {
+        while let Ok(progress) = progress_rx.recv() {
+            let fraction = if progress.total_samples > 0 {
+                progress.samples_encoded as f64 / progress.total_samples as f64
+            } else {
+                0.0
+            };
+            emit_progress(&progress_app, "converting", band_percent(55, 90, fraction), "Converting and trimming...");
         }
-    };
+    });
 
-    // Stage 2: Converting with trim (50-90%)
-    emit_progress(&app, "converting", 55, "Converting and trimming to MP3...");
+    let convert_input = actual_temp_path.clone();
+    let convert_output = final_path.clone();
+    let convert_format = format.clone();
+    let convert_task = tauri::async_runtime::spawn_blocking(move || {
+        convert_trimmed(&convert_input, &convert_output, &convert_format, start_time, end_time, Some(progress_tx))
+    });
 
-    convert_to_mp3_trimmed(&actual_temp_path, &final_path, 192, start_time, end_time)
-        .map_err(|e| DownloadError::ConversionError(e).to_string())?;
+    let convert_result = convert_task
+        .await
+        .map_err(|e| DownloadError::ConversionError(format!("Conversion task panicked: {}", e)).to_string())?;
+    let _ = progress_task.await;
+    convert_result.map_err(|e| DownloadError::ConversionError(e).to_string())?;
 
     emit_progress(&app, "converting", 90, "Conversion complete");
 
     // Stage 3: Tagging (90-100%)
     emit_progress(&app, "tagging", 92, "Writing metadata...");
 
-    let mut tag = id3::Tag::new();
-    tag.set_title(&title);
-    tag.set_artist(&artist);
-
-    // Embed album art if thumbnail URL provided
-    if let Some(ref thumb_url) = thumbnail_url {
-        emit_progress(&app, "tagging", 94, "Downloading album art...");
-        if let Ok(image_data) = download_thumbnail(thumb_url) {
-            let mime_type = if thumb_url.contains(".png") { "image/png" } else { "image/jpeg" };
-            let picture = id3::frame::Picture {
-                mime_type: mime_type.to_string(),
-                picture_type: id3::frame::PictureType::CoverFront,
-                description: "Cover".to_string(),
-                data: image_data,
-            };
-            tag.add_frame(picture);
+    let thumbnail_bytes = match &thumbnail_url {
+        Some(thumb_url) => {
+            emit_progress(&app, "tagging", 94, "Downloading album art...");
+            download_thumbnail(thumb_url).ok()
         }
-    }
+        None => None,
+    };
+
+    let duration_ms = ((end_time - start_time) * 1000.0).max(0.0) as u64;
+    let settings = load_settings();
+    let lyrics = resolve_lyrics(&settings, &url, &artist, &title, Some(end_time - start_time), &final_path);
 
-    tag.write_to_path(&final_path, id3::Version::Id3v24)
-        .map_err(|e| DownloadError::FileError(format!("Failed to write ID3 tags: {}", e)).to_string())?;
+    tagging::write_metadata(
+        &final_path,
+        &format,
+        &TrackTags { title: &title, artist: &artist, album: None, duration_ms: Some(duration_ms), year: None, track_number: None, genre: None },
+        thumbnail_bytes.as_deref(),
+        lyrics.as_ref(),
+    )
+    .map_err(|e| DownloadError::FileError(e).to_string())?;
 
     emit_progress(&app, "tagging", 98, "Cleaning up...");
 
-    // Remove the temporary m4a file
+    // Remove the temporary download file
     let _ = std::fs::remove_file(&actual_temp_path);
 
     // Save to download history
@@ -1362,9 +1797,14 @@ async fn download_audio_trimmed(
     })
 }
 
-/// Get current yt-dlp version
+/// Get the current yt-dlp version: the managed standalone binary's if one
+/// has been installed via `update_ytdlp`, otherwise the embedded module's.
 #[tauri::command]
 async fn get_ytdlp_version() -> Result<String, String> {
+    if let Some(version) = ytdlp_update::installed_version() {
+        return Ok(version);
+    }
+
     init_python_env();
     Python::with_gil(|py| {
         let yt_dlp = py.import("yt_dlp").map_err(|e| format!("Failed to import yt_dlp: {}", e))?;
@@ -1376,68 +1816,40 @@ async fn get_ytdlp_version() -> Result<String, String> {
     })
 }
 
-/// Check for yt-dlp updates by comparing with PyPI
+/// Check for yt-dlp updates by comparing the installed version against
+/// GitHub's latest release tag - no PyPI/pip dependency required.
 #[tauri::command]
 async fn check_ytdlp_update() -> Result<Option<String>, String> {
-    // Get current version
     let current_version = get_ytdlp_version().await?;
-
-    // Fetch latest version from PyPI
-    let client = reqwest::blocking::Client::new();
-    let response = client.get("https://pypi.org/pypi/yt-dlp/json")
-        .timeout(std::time::Duration::from_secs(10))
-        .send()
-        .map_err(|e| format!("Failed to check for updates: {}", e))?;
-
-    if !response.status().is_success() {
-        return Err("Failed to fetch version info from PyPI".to_string());
-    }
-
-    let json: serde_json::Value = response.json()
-        .map_err(|e| format!("Failed to parse PyPI response: {}", e))?;
-
-    let latest_version = json["info"]["version"]
-        .as_str()
-        .ok_or("Failed to get latest version from PyPI")?
-        .to_string();
-
-    // Compare versions
-    if latest_version != current_version {
-        Ok(Some(latest_version))
-    } else {
-        Ok(None)
-    }
+    ytdlp_update::check_for_update(&current_version)
 }
 
-/// Update yt-dlp to latest version
+/// Download the latest standalone yt-dlp binary, verify its checksum, and
+/// install it as the managed binary - no Python/pip installation required on
+/// the target machine. Downloads and metadata lookups use it only where
+/// `ytdlp_config` routes them to it.
 #[tauri::command]
 async fn update_ytdlp(app: tauri::AppHandle) -> Result<String, String> {
-    use std::process::Command;
-
-    // Emit progress
-    let _ = app.emit("ytdlp-update-progress", "Starting update...");
-
-    // Run pip install --upgrade yt-dlp
-    let output = Command::new("pip3")
-        .args(["install", "--upgrade", "yt-dlp"])
-        .output()
-        .map_err(|e| format!("Failed to run pip: {}", e))?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("Update failed: {}", stderr));
-    }
-
-    let _ = app.emit("ytdlp-update-progress", "Update complete!");
-
-    // Get the new version
-    get_ytdlp_version().await
+    let progress_app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || ytdlp_update::download_and_install(&progress_app, "ytdlp-update-progress"))
+        .await
+        .map_err(|e| format!("Update task panicked: {}", e))?
 }
 
-/// Convert M4A/AAC to MP3 with time trimming
-fn convert_to_mp3_trimmed(input_path: &Path, output_path: &Path, bitrate_kbps: u32, start_time: f64, end_time: f64) -> Result<(), String> {
+/// Decode, trim to `[start_time, end_time)`, and encode into `format`,
+/// sharing the same `AudioEncoder` abstraction `formats::convert_audio` uses
+/// so trimmed downloads aren't stuck on a hardcoded MP3 path.
+fn convert_trimmed(
+    input_path: &Path,
+    output_path: &Path,
+    format: &OutputFormat,
+    start_time: f64,
+    end_time: f64,
+    progress_tx: Option<std::sync::mpsc::Sender<TrimProgress>>,
+) -> Result<(), String> {
     let file = File::open(input_path).map_err(|e| format!("Failed to open input file: {}", e))?;
     let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let is_seekable = mss.is_seekable();
 
     let mut hint = Hint::new();
     if let Some(ext) = input_path.extension() {
@@ -1448,9 +1860,10 @@ fn convert_to_mp3_trimmed(input_path: &Path, output_path: &Path, bitrate_kbps: u
         .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())
         .map_err(|e| format!("Failed to probe audio format: {}", e))?;
 
-    let mut format = probed.format;
+    let mut demuxed = probed.format;
 
-    let track = format.tracks()
+    let track = demuxed
+        .tracks()
         .iter()
         .find(|t| t.codec_params.codec != symphonia::core::codecs::CODEC_TYPE_NULL)
         .ok_or("No audio track found")?;
@@ -1467,28 +1880,44 @@ fn convert_to_mp3_trimmed(input_path: &Path, output_path: &Path, bitrate_kbps: u
     let start_sample = (start_time * sample_rate as f64) as u64;
     let end_sample = (end_time * sample_rate as f64) as u64;
 
-    // Create MP3 encoder
-    let mut mp3_encoder = Builder::new().ok_or("Failed to create MP3 encoder")?;
-    mp3_encoder.set_num_channels(channels as u8).map_err(|e| format!("Failed to set channels: {:?}", e))?;
-    mp3_encoder.set_sample_rate(sample_rate).map_err(|e| format!("Failed to set sample rate: {:?}", e))?;
-    mp3_encoder.set_brate(match bitrate_kbps {
-        128 => mp3lame_encoder::Bitrate::Kbps128,
-        192 => mp3lame_encoder::Bitrate::Kbps192,
-        256 => mp3lame_encoder::Bitrate::Kbps256,
-        320 => mp3lame_encoder::Bitrate::Kbps320,
-        _ => mp3lame_encoder::Bitrate::Kbps192,
-    }).map_err(|e| format!("Failed to set bitrate: {:?}", e))?;
-    mp3_encoder.set_quality(mp3lame_encoder::Quality::Best).map_err(|e| format!("Failed to set quality: {:?}", e))?;
-
-    let mut mp3_encoder = mp3_encoder.build().map_err(|e| format!("Failed to build MP3 encoder: {:?}", e))?;
+    // Jump straight to just before the clip instead of decoding (and
+    // discarding) every packet from the start of the file - a significant
+    // CPU win for clips trimmed deep into a long track. `current_sample`
+    // starts from wherever the seek actually landed rather than assuming
+    // zero, mirroring how rodio's `try_seek` reports the real post-seek
+    // position. Falls back to the linear scan below for unseekable sources
+    // or codecs that don't support seeking.
+    let mut current_sample: u64 = if is_seekable && start_sample > 0 {
+        let seek_time = symphonia::core::units::Time::new(start_time.trunc() as u64, start_time.fract());
+        match demuxed.seek(
+            symphonia::core::formats::SeekMode::Accurate,
+            symphonia::core::formats::SeekTo::Time { time: seek_time, track_id: Some(track_id) },
+        ) {
+            Ok(seeked) => {
+                decoder.reset();
+                // `required_ts` is where we asked to land, not where the
+                // seek actually put us - symphonia only seeks to a nearby
+                // keyframe/sync point, so trusting `required_ts` here bleeds
+                // pre-roll into the clip start and truncates the same
+                // amount early at the end (current_sample would be
+                // overcounted by the gap for the rest of the decode loop).
+                seeked.actual_ts
+            }
+            Err(e) => {
+                eprintln!("Warning: seek failed, falling back to linear scan: {}", e);
+                0
+            }
+        }
+    } else {
+        0
+    };
 
-    let mut output_file = File::create(output_path).map_err(|e| format!("Failed to create output file: {}", e))?;
+    let mut encoder = formats::make_encoder(format, sample_rate, channels as u8)?;
 
     let mut sample_buf: Option<SampleBuffer<i16>> = None;
-    let mut current_sample: u64 = 0;
 
     loop {
-        let packet = match format.next_packet() {
+        let packet = match demuxed.next_packet() {
             Ok(packet) => packet,
             Err(symphonia::core::errors::Error::IoError(ref e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
             Err(e) => return Err(format!("Failed to read packet: {}", e)),
@@ -1534,21 +1963,20 @@ fn convert_to_mp3_trimmed(input_path: &Path, output_path: &Path, bitrate_kbps: u
                 };
 
                 if trim_start < trim_end {
-                    let trimmed_samples = &samples[trim_start..trim_end];
-
-                    let input = InterleavedPcm(trimmed_samples);
-                    let buf_size = mp3lame_encoder::max_required_buffer_size(trimmed_samples.len());
-                    let mut mp3_out: Vec<MaybeUninit<u8>> = vec![MaybeUninit::uninit(); buf_size];
-                    let encoded_size = mp3_encoder.encode(input, &mut mp3_out)
-                        .map_err(|e| format!("Failed to encode MP3: {:?}", e))?;
-
-                    let mp3_bytes: &[u8] = unsafe { std::slice::from_raw_parts(mp3_out.as_ptr() as *const u8, encoded_size) };
-                    output_file.write_all(mp3_bytes).map_err(|e| format!("Failed to write MP3 data: {}", e))?;
+                    encoder.encode(&samples[trim_start..trim_end])?;
                 }
             }
 
             current_sample = packet_end;
 
+            if let Some(tx) = &progress_tx {
+                let encoded_so_far = current_sample.saturating_sub(start_sample).min(end_sample - start_sample);
+                let _ = tx.send(TrimProgress {
+                    samples_encoded: encoded_so_far,
+                    total_samples: end_sample - start_sample,
+                });
+            }
+
             // Stop if we've passed the end time
             if current_sample >= end_sample {
                 break;
@@ -1556,14 +1984,8 @@ fn convert_to_mp3_trimmed(input_path: &Path, output_path: &Path, bitrate_kbps: u
         }
     }
 
-    // Flush the encoder
-    let mut mp3_out: Vec<MaybeUninit<u8>> = vec![MaybeUninit::uninit(); 7200];
-    let encoded_size = mp3_encoder.flush::<FlushNoGap>(&mut mp3_out)
-        .map_err(|e| format!("Failed to flush MP3 encoder: {:?}", e))?;
-    let mp3_bytes: &[u8] = unsafe { std::slice::from_raw_parts(mp3_out.as_ptr() as *const u8, encoded_size) };
-    output_file.write_all(mp3_bytes).map_err(|e| format!("Failed to write final MP3 data: {}", e))?;
-
-    Ok(())
+    let encoded = encoder.finish()?;
+    std::fs::write(output_path, encoded).map_err(|e| format!("Failed to write output file: {}", e))
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -1582,10 +2004,13 @@ pub fn run() {
             get_default_download_dir,
             add_to_queue,
             add_urls_to_queue,
+            add_playlist_to_queue,
+            add_spotify_to_queue,
             get_queue,
             remove_from_queue,
             clear_completed,
             process_queue_item,
+            start_queue,
             get_download_history,
             search_download_history,
             delete_history_entry,
@@ -0,0 +1,89 @@
+use crate::tagging::{Lyrics, SyncedLine};
+
+/// Query lrclib.net's open lyrics API by artist/title/duration, preferring
+/// time-synced LRC lines over plain text. Returns `None` on any error or
+/// miss - lyrics are optional, like album art.
+pub fn fetch(artist: &str, title: &str, duration_secs: Option<f64>) -> Option<Lyrics> {
+    let mut query = vec![("artist_name", artist.to_string()), ("track_name", title.to_string())];
+    if let Some(duration) = duration_secs {
+        query.push(("duration", duration.round().to_string()));
+    }
+
+    let body: serde_json::Value = reqwest::blocking::Client::new()
+        .get("https://lrclib.net/api/get")
+        .query(&query)
+        .send()
+        .ok()?
+        .json()
+        .ok()?;
+
+    let synced = body
+        .get("syncedLyrics")
+        .and_then(|v| v.as_str())
+        .map(parse_lrc)
+        .filter(|lines| !lines.is_empty());
+    let plain = body.get("plainLyrics").and_then(|v| v.as_str()).map(str::to_string);
+
+    if synced.is_none() && plain.is_none() {
+        return None;
+    }
+
+    Some(Lyrics { synced, plain })
+}
+
+/// Render synced lyric lines back into standard LRC text (`[mm:ss.xx]text`
+/// per line), so a `.lrc` file can be saved regardless of which provider -
+/// lrclib or yt-dlp subtitles - produced the synced lines.
+pub fn render_lrc(lines: &[SyncedLine]) -> String {
+    lines
+        .iter()
+        .map(|line| {
+            let minutes = line.timestamp_ms / 60_000;
+            let seconds = (line.timestamp_ms % 60_000) / 1000;
+            let centis = (line.timestamp_ms % 1000) / 10;
+            format!("[{:02}:{:02}.{:02}]{}", minutes, seconds, centis, line.text)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parse standard LRC text (`[mm:ss.xx]text`, optionally several timestamp
+/// tags per line) into timestamped lyric lines.
+fn parse_lrc(lrc: &str) -> Vec<SyncedLine> {
+    let mut lines = Vec::new();
+
+    for raw_line in lrc.lines() {
+        let mut rest = raw_line;
+        let mut timestamps = Vec::new();
+        while let Some(tag) = rest.strip_prefix('[') {
+            let Some((ts, after)) = tag.split_once(']') else { break };
+            if let Some(ms) = parse_timestamp(ts) {
+                timestamps.push(ms);
+            }
+            rest = after;
+        }
+
+        let text = rest.trim().to_string();
+        if text.is_empty() {
+            continue;
+        }
+        for timestamp_ms in timestamps {
+            lines.push(SyncedLine { timestamp_ms, text: text.clone() });
+        }
+    }
+
+    lines.sort_by_key(|line| line.timestamp_ms);
+    lines
+}
+
+fn parse_timestamp(ts: &str) -> Option<u32> {
+    let (minutes, rest) = ts.split_once(':')?;
+    let (seconds, fraction) = rest.split_once('.')?;
+    let minutes: u32 = minutes.parse().ok()?;
+    let seconds: u32 = seconds.parse().ok()?;
+    let fraction_ms: u32 = match fraction.len() {
+        2 => fraction.parse::<u32>().ok()? * 10,
+        _ => fraction.parse().ok()?,
+    };
+    Some((minutes * 60 + seconds) * 1000 + fraction_ms)
+}
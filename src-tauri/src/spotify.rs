@@ -0,0 +1,226 @@
+use std::collections::HashSet;
+
+use crate::ytdlp::Metadata;
+
+/// A single track resolved from a Spotify track/album/playlist URL.
+pub struct SpotifyTrack {
+    pub title: String,
+    pub artist: String,
+    pub album: Option<String>,
+    pub duration_ms: Option<u64>,
+    pub cover_url: Option<String>,
+}
+
+pub fn is_spotify_url(url: &str) -> bool {
+    url.contains("open.spotify.com")
+}
+
+/// Resolve a Spotify track/album/playlist URL into its constituent tracks via
+/// the Spotify Web API's client-credentials flow - we only read public
+/// catalog data, so no user login is needed.
+pub fn resolve(url: &str, client_id: &str, client_secret: &str) -> Result<Vec<SpotifyTrack>, String> {
+    let token = get_access_token(client_id, client_secret)?;
+    let (kind, id) = parse_spotify_url(url)?;
+
+    match kind.as_str() {
+        "track" => Ok(vec![fetch_track(&token, &id)?]),
+        "album" => fetch_album(&token, &id),
+        "playlist" => fetch_playlist(&token, &id),
+        other => Err(format!("Unsupported Spotify URL type: {}", other)),
+    }
+}
+
+fn get_access_token(client_id: &str, client_secret: &str) -> Result<String, String> {
+    let body: serde_json::Value = reqwest::blocking::Client::new()
+        .post("https://accounts.spotify.com/api/token")
+        .basic_auth(client_id, Some(client_secret))
+        .form(&[("grant_type", "client_credentials")])
+        .send()
+        .map_err(|e| format!("Failed to request Spotify access token: {}", e))?
+        .json()
+        .map_err(|e| format!("Failed to parse Spotify token response: {}", e))?;
+
+    body.get("access_token")
+        .and_then(|t| t.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| "Spotify token response had no access_token".to_string())
+}
+
+/// Split `https://open.spotify.com/<kind>/<id>?si=...` into `(kind, id)`.
+fn parse_spotify_url(url: &str) -> Result<(String, String), String> {
+    let path = url.split("open.spotify.com/").nth(1).ok_or("Not a Spotify URL")?;
+    let mut segments = path.split('/');
+    let kind = segments.next().filter(|s| !s.is_empty()).ok_or("Missing Spotify URL type")?.to_string();
+    let id = segments
+        .next()
+        .and_then(|s| s.split(['?', '#']).next())
+        .filter(|s| !s.is_empty())
+        .ok_or("Missing Spotify URL id")?
+        .to_string();
+    Ok((kind, id))
+}
+
+fn fetch_track(token: &str, id: &str) -> Result<SpotifyTrack, String> {
+    let body: serde_json::Value = reqwest::blocking::Client::new()
+        .get(format!("https://api.spotify.com/v1/tracks/{}", id))
+        .bearer_auth(token)
+        .send()
+        .map_err(|e| format!("Failed to fetch Spotify track: {}", e))?
+        .json()
+        .map_err(|e| format!("Failed to parse Spotify track: {}", e))?;
+
+    track_from_json(&body).ok_or_else(|| "Malformed Spotify track response".to_string())
+}
+
+fn fetch_album(token: &str, id: &str) -> Result<Vec<SpotifyTrack>, String> {
+    let body: serde_json::Value = reqwest::blocking::Client::new()
+        .get(format!("https://api.spotify.com/v1/albums/{}", id))
+        .bearer_auth(token)
+        .send()
+        .map_err(|e| format!("Failed to fetch Spotify album: {}", e))?
+        .json()
+        .map_err(|e| format!("Failed to parse Spotify album: {}", e))?;
+
+    let album_name = body.get("name").and_then(|n| n.as_str()).map(str::to_string);
+    let cover_url = body
+        .get("images")
+        .and_then(|imgs| imgs.as_array())
+        .and_then(|imgs| imgs.first())
+        .and_then(|img| img.get("url"))
+        .and_then(|u| u.as_str())
+        .map(str::to_string);
+
+    let tracks = body.get("tracks").ok_or_else(|| "Malformed Spotify album response".to_string())?;
+    let items = collect_paginated_items(token, tracks)?;
+
+    Ok(items
+        .iter()
+        .filter_map(|item| {
+            let (title, artist) = title_and_artist(item)?;
+            Some(SpotifyTrack {
+                title,
+                artist,
+                album: album_name.clone(),
+                duration_ms: item.get("duration_ms").and_then(|d| d.as_u64()),
+                cover_url: cover_url.clone(),
+            })
+        })
+        .collect())
+}
+
+fn fetch_playlist(token: &str, id: &str) -> Result<Vec<SpotifyTrack>, String> {
+    let body: serde_json::Value = reqwest::blocking::Client::new()
+        .get(format!("https://api.spotify.com/v1/playlists/{}", id))
+        .bearer_auth(token)
+        .send()
+        .map_err(|e| format!("Failed to fetch Spotify playlist: {}", e))?
+        .json()
+        .map_err(|e| format!("Failed to parse Spotify playlist: {}", e))?;
+
+    let tracks = body.get("tracks").ok_or_else(|| "Malformed Spotify playlist response".to_string())?;
+    let items = collect_paginated_items(token, tracks)?;
+
+    Ok(items.iter().filter_map(|item| item.get("track")).filter_map(track_from_json).collect())
+}
+
+/// Collect every item across a paginated Spotify `tracks` object, following
+/// its `next` cursor (a full URL to the next page) until exhausted. Albums
+/// and playlists page `tracks.items` independently of the surrounding
+/// resource, so a single `items` read silently drops tracks past the first
+/// page for anything larger than one page.
+fn collect_paginated_items(token: &str, tracks: &serde_json::Value) -> Result<Vec<serde_json::Value>, String> {
+    let mut items: Vec<serde_json::Value> = tracks
+        .get("items")
+        .and_then(|i| i.as_array())
+        .cloned()
+        .ok_or_else(|| "Malformed Spotify tracks page".to_string())?;
+
+    let mut next = tracks.get("next").and_then(|n| n.as_str()).map(str::to_string);
+    while let Some(next_url) = next {
+        let page: serde_json::Value = reqwest::blocking::Client::new()
+            .get(&next_url)
+            .bearer_auth(token)
+            .send()
+            .map_err(|e| format!("Failed to fetch Spotify tracks page: {}", e))?
+            .json()
+            .map_err(|e| format!("Failed to parse Spotify tracks page: {}", e))?;
+
+        if let Some(page_items) = page.get("items").and_then(|i| i.as_array()) {
+            items.extend(page_items.iter().cloned());
+        }
+        next = page.get("next").and_then(|n| n.as_str()).map(str::to_string);
+    }
+
+    Ok(items)
+}
+
+fn track_from_json(body: &serde_json::Value) -> Option<SpotifyTrack> {
+    let (title, artist) = title_and_artist(body)?;
+    let album = body.get("album").and_then(|a| a.get("name")).and_then(|n| n.as_str()).map(str::to_string);
+    let cover_url = body
+        .get("album")
+        .and_then(|a| a.get("images"))
+        .and_then(|imgs| imgs.as_array())
+        .and_then(|imgs| imgs.first())
+        .and_then(|img| img.get("url"))
+        .and_then(|u| u.as_str())
+        .map(str::to_string);
+
+    Some(SpotifyTrack {
+        title,
+        artist,
+        album,
+        duration_ms: body.get("duration_ms").and_then(|d| d.as_u64()),
+        cover_url,
+    })
+}
+
+fn title_and_artist(body: &serde_json::Value) -> Option<(String, String)> {
+    let title = body.get("name")?.as_str()?.to_string();
+    let artist = body
+        .get("artists")?
+        .as_array()?
+        .iter()
+        .filter_map(|a| a.get("name").and_then(|n| n.as_str()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if artist.is_empty() {
+        return None;
+    }
+    Some((title, artist))
+}
+
+/// Score a yt-dlp search result against `track`: title/artist token overlap,
+/// plus a bonus when durations line up within a few seconds. Higher is
+/// better; candidates below a minimal bar are treated as non-matches.
+fn score_candidate(track: &SpotifyTrack, candidate: &Metadata) -> f64 {
+    let wanted = format!("{} {}", track.artist, track.title).to_lowercase();
+    let wanted_tokens: HashSet<&str> = wanted.split_whitespace().collect();
+    let candidate_title = candidate.title.to_lowercase();
+    let candidate_tokens: HashSet<&str> = candidate_title.split_whitespace().collect();
+
+    let overlap = wanted_tokens.intersection(&candidate_tokens).count();
+    let mut score = overlap as f64 / wanted_tokens.len().max(1) as f64;
+
+    if let (Some(track_ms), Some(candidate_secs)) = (track.duration_ms, candidate.duration) {
+        let diff = (track_ms as f64 / 1000.0 - candidate_secs).abs();
+        if diff <= 3.0 {
+            score += 1.0;
+        } else if diff <= 10.0 {
+            score += 0.3;
+        }
+    }
+
+    score
+}
+
+/// Pick the best-matching yt-dlp search result for `track`, or `None` if
+/// nothing scored above a minimal bar.
+pub fn best_match<'a>(track: &SpotifyTrack, candidates: &'a [Metadata]) -> Option<&'a Metadata> {
+    candidates
+        .iter()
+        .map(|candidate| (candidate, score_candidate(track, candidate)))
+        .filter(|(_, score)| *score > 0.3)
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(candidate, _)| candidate)
+}
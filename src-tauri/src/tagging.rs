@@ -0,0 +1,192 @@
+use id3::TagLike;
+use image::GenericImageView;
+use lofty::config::WriteOptions;
+use lofty::file::TaggedFileExt;
+use lofty::picture::{MimeType, Picture, PictureType};
+use lofty::probe::Probe;
+use lofty::tag::{Accessor, ItemKey, Tag as LoftyTag};
+use std::io::Cursor;
+use std::path::Path;
+
+use crate::formats::OutputFormat;
+
+/// The metadata fields we write into a downloaded track, independent of
+/// container format.
+pub struct TrackTags<'a> {
+    pub title: &'a str,
+    pub artist: &'a str,
+    pub album: Option<&'a str>,
+    pub duration_ms: Option<u64>,
+    pub year: Option<u32>,
+    pub track_number: Option<u32>,
+    pub genre: Option<&'a str>,
+}
+
+/// A line of synced lyrics: offset from the start of the track, and the text.
+pub struct SyncedLine {
+    pub timestamp_ms: u32,
+    pub text: String,
+}
+
+/// Lyrics fetched for a track. For MP3, synced lines are written as `SYLT`
+/// and the plain text as a single `USLT` blob, each whenever it is present.
+pub struct Lyrics {
+    pub synced: Option<Vec<SyncedLine>>,
+    pub plain: Option<String>,
+}
+
+/// Write title/artist/album/year/track number/genre, optional cover art, and
+/// optional lyrics into `path`. The common fields go through `lofty`, which
+/// understands MP3, FLAC, OGG Vorbis, Opus and WAV behind one API; MP3's
+/// synced/unsynced lyrics frames and duration (which lofty doesn't model) are
+/// layered on afterwards with `id3`.
+pub fn write_metadata(
+    path: &Path,
+    format: &OutputFormat,
+    tags: &TrackTags,
+    thumbnail: Option<&[u8]>,
+    lyrics: Option<&Lyrics>,
+) -> Result<(), String> {
+    // MP3 gets the richer SYLT/USLT frames below, written directly with
+    // `id3`; for every other container `write_common_tags` stores the plain
+    // lyrics text through lofty's generic `ItemKey::Lyrics`, since none of
+    // FLAC/OGG/Opus/WAV's tag formats have a synced-lyrics slot to target.
+    let common_lyrics = if matches!(format, OutputFormat::Mp3 { .. }) { None } else { lyrics };
+    write_common_tags(path, tags, thumbnail, common_lyrics)?;
+
+    if matches!(format, OutputFormat::Mp3 { .. }) {
+        write_id3_extras(path, tags.duration_ms, lyrics)?;
+    }
+
+    Ok(())
+}
+
+/// Write the fields every supported container has a slot for, via `lofty`'s
+/// format-agnostic tag API.
+fn write_common_tags(
+    path: &Path,
+    tags: &TrackTags,
+    thumbnail: Option<&[u8]>,
+    lyrics: Option<&Lyrics>,
+) -> Result<(), String> {
+    let mut tagged_file = Probe::open(path)
+        .map_err(|e| format!("Failed to probe {}: {}", path.display(), e))?
+        .read()
+        .map_err(|e| format!("Failed to read tags from {}: {}", path.display(), e))?;
+
+    let tag_type = tagged_file.primary_tag_type();
+    if tagged_file.tag(tag_type).is_none() {
+        tagged_file.insert_tag(LoftyTag::new(tag_type));
+    }
+    let tag = tagged_file
+        .primary_tag_mut()
+        .ok_or_else(|| "Container has no tag support".to_string())?;
+
+    tag.set_title(tags.title.to_string());
+    tag.set_artist(tags.artist.to_string());
+    if let Some(album) = tags.album {
+        tag.set_album(album.to_string());
+    }
+    if let Some(year) = tags.year {
+        tag.set_year(year);
+    }
+    if let Some(track_number) = tags.track_number {
+        tag.set_track(track_number);
+    }
+    if let Some(genre) = tags.genre {
+        tag.set_genre(genre.to_string());
+    }
+
+    if let Some(lyrics) = lyrics {
+        // No synced-lyrics item key exists outside ID3, so the best these
+        // containers can do is the plain text, preferring a rendered view of
+        // the synced lines (if that's all we have) over leaving it blank.
+        let plain = lyrics.plain.clone().or_else(|| {
+            lyrics.synced.as_ref().map(|lines| {
+                lines.iter().map(|line| line.text.as_str()).collect::<Vec<_>>().join("\n")
+            })
+        });
+        if let Some(plain) = plain {
+            tag.insert_text(ItemKey::Lyrics, plain);
+        }
+    }
+
+    if let Some(thumb) = thumbnail {
+        match to_square_jpeg(thumb) {
+            // Always re-encoded to JPEG above, so the MIME type is known
+            // from the bytes we just produced rather than guessed from a URL.
+            Ok(jpeg_bytes) => {
+                tag.push_picture(Picture::new_unchecked(
+                    PictureType::CoverFront,
+                    Some(MimeType::Jpeg),
+                    None,
+                    jpeg_bytes,
+                ));
+            }
+            Err(e) => eprintln!("Warning: failed to prepare cover art: {}", e),
+        }
+    }
+
+    tagged_file
+        .save_to_path(path, WriteOptions::default())
+        .map_err(|e| format!("Failed to write tags: {}", e))
+}
+
+/// Layer MP3's synced/unsynced lyrics frames (SYLT/USLT) and duration (TLEN)
+/// on top of the common fields `write_common_tags` already wrote.
+fn write_id3_extras(path: &Path, duration_ms: Option<u64>, lyrics: Option<&Lyrics>) -> Result<(), String> {
+    if duration_ms.is_none() && lyrics.is_none() {
+        return Ok(());
+    }
+
+    let mut tag = id3::Tag::read_from_path(path).map_err(|e| format!("Failed to read ID3 tags: {}", e))?;
+
+    if let Some(duration_ms) = duration_ms {
+        tag.add_frame(id3::frame::Frame::text("TLEN", duration_ms.to_string()));
+    }
+
+    if let Some(lyrics) = lyrics {
+        if let Some(synced) = &lyrics.synced {
+            if !synced.is_empty() {
+                tag.add_frame(id3::frame::SynchronisedLyrics {
+                    lang: "eng".to_string(),
+                    timestamp_format: id3::frame::TimestampFormat::Ms,
+                    content_type: id3::frame::SynchronisedLyricsType::Lyrics,
+                    description: String::new(),
+                    content: synced
+                        .iter()
+                        .map(|line| (line.timestamp_ms, line.text.clone()))
+                        .collect(),
+                });
+            }
+        }
+        if let Some(plain) = &lyrics.plain {
+            tag.add_frame(id3::frame::Lyrics {
+                lang: "eng".to_string(),
+                description: String::new(),
+                text: plain.clone(),
+            });
+        }
+    }
+
+    tag.write_to_path(path, id3::Version::Id3v24)
+        .map_err(|e| format!("Failed to write ID3 tags: {}", e))
+}
+
+/// Decode `thumbnail`, center-crop it to a square, and re-encode as JPEG -
+/// the shape cover art viewers expect regardless of the source thumbnail's
+/// aspect ratio.
+fn to_square_jpeg(thumbnail: &[u8]) -> Result<Vec<u8>, String> {
+    let img = image::load_from_memory(thumbnail).map_err(|e| format!("Failed to decode thumbnail: {}", e))?;
+    let (width, height) = img.dimensions();
+    let side = width.min(height);
+    let x = (width - side) / 2;
+    let y = (height - side) / 2;
+    let cropped = img.crop_imm(x, y, side, side);
+
+    let mut buf = Vec::new();
+    cropped
+        .write_to(&mut Cursor::new(&mut buf), image::ImageFormat::Jpeg)
+        .map_err(|e| format!("Failed to encode cover art: {}", e))?;
+    Ok(buf)
+}
@@ -0,0 +1,104 @@
+use std::path::{Path, PathBuf};
+
+/// The default filename template, matching the historical hardcoded
+/// `"{artist} - {title}"` naming.
+pub const DEFAULT_TEMPLATE: &str = "{artist} - {title}";
+
+/// Placeholder values available to a filename/folder template. A template
+/// may contain `/` to express subfolders, e.g.
+/// `"{albumartist}/{album}/{track:02} - {title}"`.
+pub struct TemplateFields<'a> {
+    pub artist: &'a str,
+    pub title: &'a str,
+    pub album: Option<&'a str>,
+    pub track_number: Option<u32>,
+    pub year: Option<u32>,
+    pub source: &'a str,
+}
+
+/// Fill in `template`'s placeholders, sanitize each `/`-separated path
+/// segment independently, and append `extension`. Unset placeholders (no
+/// album/track/year) render as an empty string rather than failing.
+pub fn render(template: &str, fields: &TemplateFields, extension: &str) -> String {
+    let track_padded = fields.track_number.map(|t| format!("{:02}", t)).unwrap_or_default();
+    let track = fields.track_number.map(|t| t.to_string()).unwrap_or_default();
+    let year = fields.year.map(|y| y.to_string()).unwrap_or_default();
+
+    let filled = template
+        .replace("{albumartist}", fields.artist)
+        .replace("{artist}", fields.artist)
+        .replace("{title}", fields.title)
+        .replace("{album}", fields.album.unwrap_or(""))
+        .replace("{track:02}", &track_padded)
+        .replace("{track}", &track)
+        .replace("{year}", &year)
+        .replace("{source}", fields.source);
+
+    let segments: Vec<String> = filled
+        .split('/')
+        .map(sanitize_segment)
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    format!("{}.{}", segments.join("/"), extension)
+}
+
+/// Replace characters that are illegal in filenames on at least one major OS
+/// - the same forbidden-character set every download path used to inline.
+pub fn sanitize_segment(segment: &str) -> String {
+    segment
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            _ => c,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Join `output_dir` with a rendered template path, creating any
+/// intermediate folders, and append a `(2)`, `(3)`, ... counter to the
+/// filename if it's already taken - repeat downloads never silently overwrite
+/// or get skipped as "already exists". The queue now downloads concurrently,
+/// so the returned path is reserved atomically (`create_new`) rather than
+/// merely checked with `exists()` first - two workers racing on the same
+/// rendered name can't both pass the check before either creates the file.
+pub fn resolve_path(output_dir: &Path, rendered: &str) -> Result<PathBuf, String> {
+    let candidate = output_dir.join(rendered);
+
+    if let Some(parent) = candidate.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create output directory: {}", e))?;
+    }
+
+    if try_reserve(&candidate)? {
+        return Ok(candidate);
+    }
+
+    let parent = candidate.parent().unwrap_or(output_dir).to_path_buf();
+    let stem = candidate.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let ext = candidate.extension().map(|s| s.to_string_lossy().to_string());
+
+    for counter in 2.. {
+        let name = match &ext {
+            Some(ext) => format!("{} ({}).{}", stem, counter, ext),
+            None => format!("{} ({})", stem, counter),
+        };
+        let next = parent.join(name);
+        if try_reserve(&next)? {
+            return Ok(next);
+        }
+    }
+
+    unreachable!("counter loop is unbounded")
+}
+
+/// Atomically claims `path` by creating it if and only if it doesn't already
+/// exist, returning `false` (not an error) when someone else won the race.
+fn try_reserve(path: &Path) -> Result<bool, String> {
+    match std::fs::OpenOptions::new().write(true).create_new(true).open(path) {
+        Ok(_) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+        Err(e) => Err(format!("Failed to reserve output path: {}", e)),
+    }
+}
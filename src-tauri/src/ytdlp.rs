@@ -0,0 +1,524 @@
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PyTuple};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use crate::init_python_env;
+
+/// A snapshot of yt-dlp's progress dict for one `progress_hooks` callback.
+#[derive(Debug, Clone)]
+pub struct DownloadProgress {
+    pub downloaded_bytes: u64,
+    pub total_bytes: Option<u64>,
+    pub speed: Option<f64>,
+    pub eta: Option<u64>,
+    pub status: String,
+}
+
+/// Called from inside yt-dlp's download loop (embedded backend only) with
+/// live byte/speed/ETA figures.
+pub type ProgressHook = Arc<dyn Fn(DownloadProgress) + Send + Sync>;
+/// Polled from the same callback; returning `true` aborts the download.
+pub type CancelCheck = Arc<dyn Fn() -> bool + Send + Sync>;
+
+/// Which yt-dlp implementation to talk to. The embedded PyO3 path is the
+/// historical default; the external path shells out to a real `yt-dlp`
+/// binary, which is more robust in bundled builds that can't guarantee an
+/// importable `yt_dlp` module.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type")]
+pub enum Backend {
+    Embedded,
+    External {
+        executable_path: String,
+        #[serde(default)]
+        extra_args: Vec<String>,
+    },
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        Backend::Embedded
+    }
+}
+
+/// Whether `import yt_dlp` actually succeeds in this process's Python
+/// environment. A clean install with no bundled Python `yt_dlp` module
+/// would otherwise silently fail every download on the `Embedded` default -
+/// callers use this to fall back to the managed/checksum-verified binary
+/// instead. Checked once and cached: the embedded interpreter's module set
+/// doesn't change over the life of the process.
+pub fn embedded_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        init_python_env();
+        Python::with_gil(|py| py.import("yt_dlp").is_ok())
+    })
+}
+
+/// Extractor knobs that apply to both metadata extraction and download,
+/// surfaced through `Settings` so the UI can tune network behavior without
+/// a rebuild.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExtractorOptions {
+    pub socket_timeout_secs: Option<u32>,
+    pub retries: Option<u32>,
+    pub fragment_retries: Option<u32>,
+    pub cookies_from_browser: Option<String>,
+    pub cookies_file: Option<String>,
+    pub rate_limit: Option<String>,
+    pub format: Option<String>,
+    /// Which YouTube player client yt-dlp impersonates (`android`, `ios`,
+    /// `web`, `tv`, ...). Switching clients is the usual workaround when
+    /// YouTube starts throwing "Sign in to confirm you're not a bot".
+    pub player_client: Option<String>,
+    /// A pre-generated `youtube:po_token` value, required by some player
+    /// clients to pass YouTube's proof-of-origin check.
+    pub po_token: Option<String>,
+}
+
+impl Default for ExtractorOptions {
+    fn default() -> Self {
+        Self {
+            socket_timeout_secs: None,
+            retries: Some(3),
+            fragment_retries: Some(3),
+            cookies_from_browser: None,
+            cookies_file: None,
+            rate_limit: None,
+            player_client: None,
+            po_token: None,
+            format: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Metadata {
+    pub id: String,
+    pub title: String,
+    pub channel: Option<String>,
+    pub uploader: Option<String>,
+    pub artist: Option<String>,
+    pub track: Option<String>,
+    pub thumbnail: Option<String>,
+    pub duration: Option<f64>,
+}
+
+/// `true` when `message` looks like a transient network failure worth
+/// retrying, rather than e.g. an invalid URL or unsupported site.
+fn is_retryable(message: &str) -> bool {
+    let lower = message.to_lowercase();
+    lower.contains("network") || lower.contains("timeout") || lower.contains("connection") || lower.contains("temporary failure")
+}
+
+/// Retry `f` up to `opts.retries` times with exponential backoff (1s, 2s,
+/// 4s, ...), but only for errors that look transient/network-related.
+fn with_retry<T>(opts: &ExtractorOptions, mut f: impl FnMut() -> Result<T, String>) -> Result<T, String> {
+    let max_retries = opts.retries.unwrap_or(3);
+    let mut attempt = 0;
+
+    loop {
+        match f() {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < max_retries && is_retryable(&e) => {
+                let backoff = Duration::from_secs(1 << attempt.min(5));
+                std::thread::sleep(backoff);
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Fetch video metadata without downloading.
+pub fn extract_info(backend: &Backend, url: &str, opts: &ExtractorOptions) -> Result<Metadata, String> {
+    with_retry(opts, || match backend {
+        Backend::Embedded => extract_info_embedded(url, opts),
+        Backend::External { executable_path, extra_args } => {
+            extract_info_external(executable_path, extra_args, url, opts)
+        }
+    })
+}
+
+/// Run a `ytsearchN:` query and return up to `count` candidate results
+/// without downloading anything - used to match a track from another source
+/// (e.g. Spotify) to a YouTube video.
+pub fn search(backend: &Backend, query: &str, count: u32, opts: &ExtractorOptions) -> Result<Vec<Metadata>, String> {
+    let search_url = format!("ytsearch{}:{}", count.max(1), query);
+    with_retry(opts, || match backend {
+        Backend::Embedded => search_embedded(&search_url, opts),
+        Backend::External { executable_path, extra_args } => {
+            search_external(executable_path, extra_args, &search_url, opts)
+        }
+    })
+}
+
+/// Turn a caller-chosen `output_path` into a yt-dlp output template that
+/// substitutes the source's real extension (`%(ext)s`) instead of whatever
+/// placeholder extension `output_path` happens to carry. Callers no longer
+/// force a transcode to a fixed container, so the file yt-dlp actually
+/// writes can be anything (opus, m4a, webm audio, ...) - the caller locates
+/// it afterwards by matching on the stem rather than assuming the extension
+/// it asked for.
+fn output_template(output_path: &str) -> String {
+    format!("{}.%(ext)s", Path::new(output_path).with_extension("").display())
+}
+
+/// Download the best audio stream for `url` into `output_path`. `on_progress`
+/// and `is_cancelled`, when given, only fire for the embedded backend - the
+/// external CLI backend has no equivalent of yt-dlp's `progress_hooks`.
+pub fn download(
+    backend: &Backend,
+    url: &str,
+    output_path: &str,
+    opts: &ExtractorOptions,
+    on_progress: Option<ProgressHook>,
+    is_cancelled: Option<CancelCheck>,
+) -> Result<String, String> {
+    with_retry(opts, || match backend {
+        Backend::Embedded => download_embedded(url, output_path, opts, on_progress.clone(), is_cancelled.clone()),
+        Backend::External { executable_path, extra_args } => {
+            download_external(executable_path, extra_args, url, output_path, opts)
+        }
+    })
+}
+
+/// Apply the shared extractor knobs to a PyO3 options dict.
+fn apply_extractor_options(opts_dict: &Bound<'_, PyDict>, opts: &ExtractorOptions) -> Result<(), String> {
+    if let Some(timeout) = opts.socket_timeout_secs {
+        opts_dict.set_item("socket_timeout", timeout).map_err(|e| e.to_string())?;
+    }
+    if let Some(retries) = opts.retries {
+        opts_dict.set_item("retries", retries).map_err(|e| e.to_string())?;
+    }
+    if let Some(fragment_retries) = opts.fragment_retries {
+        opts_dict.set_item("fragment_retries", fragment_retries).map_err(|e| e.to_string())?;
+    }
+    if let Some(browser) = &opts.cookies_from_browser {
+        opts_dict.set_item("cookiesfrombrowser", (browser,)).map_err(|e| e.to_string())?;
+    }
+    if let Some(cookies_file) = &opts.cookies_file {
+        opts_dict.set_item("cookiefile", cookies_file).map_err(|e| e.to_string())?;
+    }
+    if let Some(rate_limit) = &opts.rate_limit {
+        opts_dict.set_item("ratelimit", rate_limit).map_err(|e| e.to_string())?;
+    }
+    if let Some(format) = &opts.format {
+        opts_dict.set_item("format", format).map_err(|e| e.to_string())?;
+    }
+    if opts.player_client.is_some() || opts.po_token.is_some() {
+        let youtube_args = PyDict::new(opts_dict.py());
+        if let Some(player_client) = &opts.player_client {
+            youtube_args.set_item("player_client", (player_client,)).map_err(|e| e.to_string())?;
+        }
+        if let Some(po_token) = &opts.po_token {
+            youtube_args.set_item("po_token", (po_token,)).map_err(|e| e.to_string())?;
+        }
+        let extractor_args = PyDict::new(opts_dict.py());
+        extractor_args.set_item("youtube", youtube_args).map_err(|e| e.to_string())?;
+        opts_dict.set_item("extractor_args", extractor_args).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn extract_info_embedded(url: &str, opts: &ExtractorOptions) -> Result<Metadata, String> {
+    init_python_env();
+    Python::with_gil(|py| {
+        let yt_dlp = py.import("yt_dlp").map_err(|e| format!("Failed to import yt_dlp: {}", e))?;
+
+        let opts_dict = PyDict::new(py);
+        opts_dict.set_item("quiet", true).unwrap();
+        opts_dict.set_item("no_warnings", true).unwrap();
+        opts_dict.set_item("extract_flat", false).unwrap();
+        opts_dict.set_item("noplaylist", true).unwrap();
+        apply_extractor_options(&opts_dict, opts)?;
+
+        let ydl_class = yt_dlp.getattr("YoutubeDL").map_err(|e| format!("Failed to get YoutubeDL: {}", e))?;
+        let ydl = ydl_class.call1((opts_dict,)).map_err(|e| format!("Failed to create YoutubeDL: {}", e))?;
+
+        let info = ydl.call_method1("extract_info", (url, false))
+            .map_err(|e| format!("Failed to extract info: {}", e))?;
+
+        fn get_str(info: &Bound<'_, PyAny>, key: &str) -> Option<String> {
+            info.get_item(key).ok().and_then(|v| if v.is_none() { None } else { v.extract().ok() })
+        }
+        fn get_f64(info: &Bound<'_, PyAny>, key: &str) -> Option<f64> {
+            info.get_item(key).ok().and_then(|v| if v.is_none() { None } else { v.extract().ok() })
+        }
+
+        Ok(Metadata {
+            id: get_str(&info, "id").ok_or("No id field")?,
+            title: get_str(&info, "title").ok_or("No title field")?,
+            channel: get_str(&info, "channel"),
+            uploader: get_str(&info, "uploader"),
+            artist: get_str(&info, "artist"),
+            track: get_str(&info, "track"),
+            thumbnail: get_str(&info, "thumbnail"),
+            duration: get_f64(&info, "duration"),
+        })
+    })
+}
+
+fn search_embedded(search_url: &str, opts: &ExtractorOptions) -> Result<Vec<Metadata>, String> {
+    init_python_env();
+    Python::with_gil(|py| {
+        let yt_dlp = py.import("yt_dlp").map_err(|e| format!("Failed to import yt_dlp: {}", e))?;
+
+        let opts_dict = PyDict::new(py);
+        opts_dict.set_item("quiet", true).unwrap();
+        opts_dict.set_item("no_warnings", true).unwrap();
+        opts_dict.set_item("extract_flat", false).unwrap();
+        apply_extractor_options(&opts_dict, opts)?;
+
+        let ydl_class = yt_dlp.getattr("YoutubeDL").map_err(|e| format!("Failed to get YoutubeDL: {}", e))?;
+        let ydl = ydl_class.call1((opts_dict,)).map_err(|e| format!("Failed to create YoutubeDL: {}", e))?;
+
+        let info = ydl.call_method1("extract_info", (search_url, false))
+            .map_err(|e| format!("Failed to search: {}", e))?;
+
+        let entries = info.get_item("entries").map_err(|e| format!("Failed to read search results: {}", e))?;
+
+        let mut results = Vec::new();
+        for entry in entries.try_iter().map_err(|e| format!("Failed to iterate search results: {}", e))? {
+            let entry = entry.map_err(|e| format!("Failed to read search result: {}", e))?;
+            let Some(id) = get_string(&entry, "id") else { continue };
+            results.push(Metadata {
+                id,
+                title: get_string(&entry, "title").unwrap_or_default(),
+                channel: get_string(&entry, "channel"),
+                uploader: get_string(&entry, "uploader"),
+                artist: get_string(&entry, "artist"),
+                track: get_string(&entry, "track"),
+                thumbnail: get_string(&entry, "thumbnail"),
+                duration: get_f64(&entry, "duration"),
+            });
+        }
+        Ok(results)
+    })
+}
+
+fn download_embedded(
+    url: &str,
+    output_path: &str,
+    opts: &ExtractorOptions,
+    on_progress: Option<ProgressHook>,
+    is_cancelled: Option<CancelCheck>,
+) -> Result<String, String> {
+    init_python_env();
+    Python::with_gil(|py| {
+        let yt_dlp = py.import("yt_dlp").map_err(|e| format!("Failed to import yt_dlp: {}", e))?;
+
+        let opts_dict = PyDict::new(py);
+        opts_dict.set_item("quiet", true).unwrap();
+        opts_dict.set_item("no_warnings", true).unwrap();
+        opts_dict.set_item("noplaylist", true).unwrap();
+        opts_dict.set_item("format", opts.format.clone().unwrap_or_else(|| "bestaudio[ext=m4a]/bestaudio/best".to_string())).unwrap();
+        opts_dict.set_item("outtmpl", output_template(output_path)).unwrap();
+        apply_extractor_options(&opts_dict, opts)?;
+
+        if on_progress.is_some() || is_cancelled.is_some() {
+            let hook = make_progress_hook(py, on_progress, is_cancelled)
+                .map_err(|e| format!("Failed to build progress hook: {}", e))?;
+            let hooks_list = PyList::new(py, &[hook]).map_err(|e| format!("Failed to create list: {}", e))?;
+            opts_dict.set_item("progress_hooks", hooks_list).unwrap();
+        }
+
+        // No `FFmpegExtractAudio` postprocessor here - that used to force
+        // every download through a lossy AAC/m4a transcode before
+        // `formats::convert_audio` got a chance to re-encode it, which
+        // defeated lossless output formats entirely. Deliver whatever
+        // container/codec the source actually served; the caller locates
+        // the resulting file by stem and symphonia decodes it directly.
+        let ydl_class = yt_dlp.getattr("YoutubeDL").map_err(|e| format!("Failed to get YoutubeDL: {}", e))?;
+        let ydl = ydl_class.call1((opts_dict,)).map_err(|e| format!("Failed to create YoutubeDL: {}", e))?;
+
+        ydl.call_method1("download", (vec![url],))
+            .map_err(|e| format!("Failed to download: {}", e))?;
+
+        Ok(output_path.to_string())
+    })
+}
+
+/// Build a Python callable suitable for yt-dlp's `progress_hooks` list: reads
+/// the status dict it's handed, forwards the parsed fields to `on_progress`,
+/// and raises to abort the download when `is_cancelled` reports true.
+fn make_progress_hook<'py>(
+    py: Python<'py>,
+    on_progress: Option<ProgressHook>,
+    is_cancelled: Option<CancelCheck>,
+) -> PyResult<Bound<'py, pyo3::types::PyCFunction>> {
+    pyo3::types::PyCFunction::new_closure(
+        py,
+        None,
+        None,
+        move |args: &Bound<'_, PyTuple>, _kwargs| -> PyResult<()> {
+            if let Some(check) = &is_cancelled {
+                if check() {
+                    return Err(pyo3::exceptions::PyRuntimeError::new_err("Download cancelled"));
+                }
+            }
+
+            if let Some(on_progress) = &on_progress {
+                let status = args.get_item(0)?;
+                let downloaded_bytes = get_u64(&status, "downloaded_bytes").unwrap_or(0);
+                let total_bytes = get_u64(&status, "total_bytes").or_else(|| get_u64(&status, "total_bytes_estimate"));
+                let speed = get_f64(&status, "speed");
+                let eta = get_u64(&status, "eta");
+                let phase = get_string(&status, "status").unwrap_or_else(|| "downloading".to_string());
+                on_progress(DownloadProgress { downloaded_bytes, total_bytes, speed, eta, status: phase });
+            }
+
+            Ok(())
+        },
+    )
+}
+
+fn get_u64(dict: &Bound<'_, PyAny>, key: &str) -> Option<u64> {
+    dict.get_item(key).ok().and_then(|v| if v.is_none() { None } else { v.extract().ok() })
+}
+
+fn get_f64(dict: &Bound<'_, PyAny>, key: &str) -> Option<f64> {
+    dict.get_item(key).ok().and_then(|v| if v.is_none() { None } else { v.extract().ok() })
+}
+
+fn get_string(dict: &Bound<'_, PyAny>, key: &str) -> Option<String> {
+    dict.get_item(key).ok().and_then(|v| if v.is_none() { None } else { v.extract().ok() })
+}
+
+/// Build the timeout/retry/cookie/rate-limit/format/extractor-args CLI flags shared by the
+/// subprocess extract and download paths.
+fn extractor_args(opts: &ExtractorOptions) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(timeout) = opts.socket_timeout_secs {
+        args.push("--socket-timeout".to_string());
+        args.push(timeout.to_string());
+    }
+    if let Some(retries) = opts.retries {
+        args.push("--retries".to_string());
+        args.push(retries.to_string());
+    }
+    if let Some(fragment_retries) = opts.fragment_retries {
+        args.push("--fragment-retries".to_string());
+        args.push(fragment_retries.to_string());
+    }
+    if let Some(browser) = &opts.cookies_from_browser {
+        args.push("--cookies-from-browser".to_string());
+        args.push(browser.clone());
+    }
+    if let Some(cookies_file) = &opts.cookies_file {
+        args.push("--cookies".to_string());
+        args.push(cookies_file.clone());
+    }
+    if let Some(rate_limit) = &opts.rate_limit {
+        args.push("--limit-rate".to_string());
+        args.push(rate_limit.clone());
+    }
+    if let Some(format) = &opts.format {
+        args.push("--format".to_string());
+        args.push(format.clone());
+    }
+    if opts.player_client.is_some() || opts.po_token.is_some() {
+        let mut youtube_args = Vec::new();
+        if let Some(player_client) = &opts.player_client {
+            youtube_args.push(format!("player_client={}", player_client));
+        }
+        if let Some(po_token) = &opts.po_token {
+            youtube_args.push(format!("po_token={}", po_token));
+        }
+        args.push("--extractor-args".to_string());
+        args.push(format!("youtube:{}", youtube_args.join(";")));
+    }
+    args
+}
+
+fn extract_info_external(executable_path: &str, extra_args: &[String], url: &str, opts: &ExtractorOptions) -> Result<Metadata, String> {
+    let output = Command::new(executable_path)
+        .arg("--dump-single-json")
+        .arg("--no-playlist")
+        .args(extractor_args(opts))
+        .args(extra_args)
+        .arg(url)
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", executable_path, e))?;
+
+    if !output.status.success() {
+        return Err(format!("{} failed: {}", executable_path, String::from_utf8_lossy(&output.stderr)));
+    }
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse {} output: {}", executable_path, e))?;
+
+    Ok(Metadata {
+        id: json["id"].as_str().ok_or("No id field")?.to_string(),
+        title: json["title"].as_str().ok_or("No title field")?.to_string(),
+        channel: json["channel"].as_str().map(str::to_string),
+        uploader: json["uploader"].as_str().map(str::to_string),
+        artist: json["artist"].as_str().map(str::to_string),
+        track: json["track"].as_str().map(str::to_string),
+        thumbnail: json["thumbnail"].as_str().map(str::to_string),
+        duration: json["duration"].as_f64(),
+    })
+}
+
+fn search_external(executable_path: &str, extra_args: &[String], search_url: &str, opts: &ExtractorOptions) -> Result<Vec<Metadata>, String> {
+    let output = Command::new(executable_path)
+        .arg("--dump-single-json")
+        .args(extractor_args(opts))
+        .args(extra_args)
+        .arg(search_url)
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", executable_path, e))?;
+
+    if !output.status.success() {
+        return Err(format!("{} failed: {}", executable_path, String::from_utf8_lossy(&output.stderr)));
+    }
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse {} output: {}", executable_path, e))?;
+
+    let entries = json.get("entries").and_then(|e| e.as_array()).cloned().unwrap_or_default();
+    Ok(entries
+        .iter()
+        .filter_map(|entry| {
+            Some(Metadata {
+                id: entry["id"].as_str()?.to_string(),
+                title: entry["title"].as_str().unwrap_or_default().to_string(),
+                channel: entry["channel"].as_str().map(str::to_string),
+                uploader: entry["uploader"].as_str().map(str::to_string),
+                artist: entry["artist"].as_str().map(str::to_string),
+                track: entry["track"].as_str().map(str::to_string),
+                thumbnail: entry["thumbnail"].as_str().map(str::to_string),
+                duration: entry["duration"].as_f64(),
+            })
+        })
+        .collect())
+}
+
+fn download_external(executable_path: &str, extra_args: &[String], url: &str, output_path: &str, opts: &ExtractorOptions) -> Result<String, String> {
+    // No `--extract-audio`/`--audio-format m4a` here - that used to force
+    // every download through a lossy AAC/m4a transcode before
+    // `formats::convert_audio` got a chance to re-encode it, which defeated
+    // lossless output formats entirely. `-o` uses `%(ext)s` so yt-dlp writes
+    // whatever container/codec the source actually served.
+    let output = Command::new(executable_path)
+        .arg("--no-playlist")
+        .arg("-f")
+        .arg(opts.format.clone().unwrap_or_else(|| "bestaudio[ext=m4a]/bestaudio/best".to_string()))
+        .arg("-o")
+        .arg(output_template(output_path))
+        .args(extractor_args(opts))
+        .args(extra_args)
+        .arg(url)
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", executable_path, e))?;
+
+    if !output.status.success() {
+        return Err(format!("{} failed: {}", executable_path, String::from_utf8_lossy(&output.stderr)));
+    }
+
+    Ok(output_path.to_string())
+}
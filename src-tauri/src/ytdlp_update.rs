@@ -0,0 +1,176 @@
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+use tauri::Emitter;
+
+/// Directory the managed yt-dlp binary lives in, separate from the user's
+/// download directory so it survives even if that setting changes.
+fn managed_binary_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("sample-downloader")
+}
+
+/// Path to the managed standalone yt-dlp binary, if one has been installed.
+pub fn managed_binary_path() -> PathBuf {
+    let name = if cfg!(target_os = "windows") { "yt-dlp.exe" } else { "yt-dlp" };
+    managed_binary_dir().join(name)
+}
+
+/// The release asset name yt-dlp publishes for the current platform.
+fn platform_asset_name() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "yt-dlp.exe"
+    } else if cfg!(target_os = "macos") {
+        "yt-dlp_macos"
+    } else {
+        "yt-dlp_linux"
+    }
+}
+
+/// `--version` of the managed binary, or `None` if it hasn't been installed yet.
+pub fn installed_version() -> Option<String> {
+    let path = managed_binary_path();
+    if !path.exists() {
+        return None;
+    }
+    let output = Command::new(&path).arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The latest yt-dlp release: its tag (used as the version string) and the
+/// download URLs for this platform's binary and the published checksums file.
+struct LatestRelease {
+    tag: String,
+    binary_url: String,
+    checksums_url: String,
+}
+
+fn fetch_latest_release(client: &reqwest::blocking::Client) -> Result<LatestRelease, String> {
+    let release: serde_json::Value = client
+        .get("https://api.github.com/repos/yt-dlp/yt-dlp/releases/latest")
+        .header("User-Agent", "sample-downloader")
+        .timeout(std::time::Duration::from_secs(10))
+        .send()
+        .map_err(|e| format!("Failed to check for updates: {}", e))?
+        .json()
+        .map_err(|e| format!("Failed to parse GitHub release info: {}", e))?;
+
+    let tag = release["tag_name"].as_str().ok_or("Release response missing tag_name")?.to_string();
+
+    let asset_name = platform_asset_name();
+    let assets = release["assets"].as_array().ok_or("Release response missing assets")?;
+    let find_asset = |name: &str| {
+        assets
+            .iter()
+            .find(|a| a["name"].as_str() == Some(name))
+            .and_then(|a| a["browser_download_url"].as_str())
+            .map(str::to_string)
+    };
+
+    let binary_url = find_asset(asset_name).ok_or_else(|| format!("No release asset named {}", asset_name))?;
+    let checksums_url = find_asset("SHA2-256SUMS").ok_or("No SHA2-256SUMS asset in release")?;
+
+    Ok(LatestRelease { tag, binary_url, checksums_url })
+}
+
+/// Look up `asset_name`'s expected hash in a yt-dlp `SHA2-256SUMS` file,
+/// which lists one `<hex digest>  <filename>` pair per line.
+fn expected_checksum(checksums: &str, asset_name: &str) -> Option<String> {
+    checksums
+        .lines()
+        .find_map(|line| {
+            let (digest, name) = line.split_once("  ")?;
+            (name.trim() == asset_name).then(|| digest.trim().to_string())
+        })
+}
+
+/// Check whether a newer yt-dlp release is available, comparing against the
+/// managed binary's installed version (or `current_version` if one isn't
+/// installed yet, e.g. still embedded). Returns the new version if so.
+pub fn check_for_update(current_version: &str) -> Result<Option<String>, String> {
+    let client = reqwest::blocking::Client::new();
+    let latest = fetch_latest_release(&client)?;
+    if latest.tag == current_version { Ok(None) } else { Ok(Some(latest.tag)) }
+}
+
+/// Download the latest yt-dlp binary for this platform, verify it against
+/// the release's published SHA-256 checksum, and atomically swap it into
+/// place. Emits `progress_event` with human-readable status strings as it
+/// goes, mirroring the other long-running commands' progress events.
+pub fn download_and_install(app: &tauri::AppHandle, progress_event: &str) -> Result<String, String> {
+    let emit = |message: &str| {
+        let _ = app.emit(progress_event, message.to_string());
+    };
+
+    let client = reqwest::blocking::Client::new();
+
+    emit("Checking for the latest release...");
+    let latest = fetch_latest_release(&client)?;
+
+    emit("Downloading checksums...");
+    let checksums = client
+        .get(&latest.checksums_url)
+        .timeout(std::time::Duration::from_secs(30))
+        .send()
+        .and_then(|r| r.text())
+        .map_err(|e| format!("Failed to download checksums: {}", e))?;
+    let expected = expected_checksum(&checksums, platform_asset_name())
+        .ok_or("Checksum file didn't list this platform's binary")?;
+
+    emit("Downloading yt-dlp...");
+    let bytes = client
+        .get(&latest.binary_url)
+        .timeout(std::time::Duration::from_secs(120))
+        .send()
+        .and_then(|r| r.bytes())
+        .map_err(|e| format!("Failed to download yt-dlp: {}", e))?;
+
+    emit("Verifying checksum...");
+    let mut hasher = Sha256::new();
+    hasher.update(&bytes);
+    let actual = hex_encode(&hasher.finalize());
+    if !actual.eq_ignore_ascii_case(&expected) {
+        return Err(format!("Checksum mismatch: expected {}, got {}", expected, actual));
+    }
+
+    let dir = managed_binary_dir();
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    // Write to a temp file in the same directory first so the final rename
+    // is atomic - callers never observe a partially-written binary.
+    let final_path = managed_binary_path();
+    let tmp_path = dir.join(format!(
+        "{}.tmp",
+        final_path.file_name().and_then(|n| n.to_str()).unwrap_or("yt-dlp")
+    ));
+    {
+        let mut file =
+            std::fs::File::create(&tmp_path).map_err(|e| format!("Failed to create temp file: {}", e))?;
+        file.write_all(&bytes).map_err(|e| format!("Failed to write yt-dlp binary: {}", e))?;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut perms = std::fs::metadata(&tmp_path)
+            .map_err(|e| format!("Failed to read temp file metadata: {}", e))?
+            .permissions();
+        perms.set_mode(0o755);
+        std::fs::set_permissions(&tmp_path, perms)
+            .map_err(|e| format!("Failed to make yt-dlp executable: {}", e))?;
+    }
+
+    std::fs::rename(&tmp_path, &final_path).map_err(|e| format!("Failed to install yt-dlp: {}", e))?;
+
+    emit("Update complete!");
+    Ok(latest.tag)
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}